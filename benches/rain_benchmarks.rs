@@ -66,6 +66,32 @@ fn vertical_worm_benchmark(c: &mut Criterion) {
     });
 }
 
+fn mid_screen_update_benchmark(c: &mut Criterion) {
+    c.bench_function("benchmark_raindrop_update_mid_screen_1000", |b| {
+        let mut rng = rand::rng();
+        let options = get_sane_options();
+        b.iter(|| {
+            for index in 1..=1000 {
+                let mut drop = rain_drop::RainDrop::from_values(
+                    index,
+                    vec!['a'; 10],
+                    rain_drop::RainDropStyle::Front,
+                    10,
+                    20.0,
+                    10,
+                    10,
+                );
+                drop.update(
+                    (80, 400),
+                    &options,
+                    Duration::from_millis(50),
+                    &mut rng,
+                );
+            }
+        })
+    });
+}
+
 fn digital_rain_benchmark(c: &mut Criterion) {
     c.bench_function("benchmark_rain_new", |b| {
         b.iter(|| {
@@ -89,6 +115,7 @@ criterion_group!(
     benches,
     run_loop_benchmark,
     vertical_worm_benchmark,
+    mid_screen_update_benchmark,
     digital_rain_benchmark
 );
 criterion_main!(benches);
@@ -26,30 +26,33 @@ pub trait TerminalEffect {
     fn update_size(&mut self, width: u16, height: u16);
     /// Reset effect, i think it's useful in case of size/options update
     fn reset(&mut self);
+    /// Text printed for cell `symbol` when it stands for a glyph of several
+    /// code points, effects packing such glyphs into one char resolve them
+    fn cluster(&self, _symbol: char) -> Option<&str> {
+        None
+    }
 }
 
 pub fn process_input() -> Result<bool> {
-    if event::poll(Duration::from_millis(10))? {
-        if let event::Event::Key(keyevent) = event::read()? {
-            if keyevent
+    if event::poll(Duration::from_millis(10))?
+        && let event::Event::Key(keyevent) = event::read()?
+        && (keyevent
+            == event::KeyEvent::new(
+                event::KeyCode::Char('q'),
+                event::KeyModifiers::NONE,
+            )
+            || keyevent
                 == event::KeyEvent::new(
-                    event::KeyCode::Char('q'),
+                    event::KeyCode::Esc,
                     event::KeyModifiers::NONE,
                 )
-                || keyevent
-                    == event::KeyEvent::new(
-                        event::KeyCode::Esc,
-                        event::KeyModifiers::NONE,
-                    )
-                || keyevent
-                    == event::KeyEvent::new(
-                        event::KeyCode::Char('c'),
-                        event::KeyModifiers::CONTROL,
-                    )
-            {
-                return Ok(false);
-            }
-        }
+            || keyevent
+                == event::KeyEvent::new(
+                    event::KeyCode::Char('c'),
+                    event::KeyModifiers::CONTROL,
+                ))
+    {
+        return Ok(false);
     }
     Ok(true)
 }
@@ -99,9 +102,14 @@ where
             let (x, y, cell) = item;
             debug_assert!(*x < width as usize && *y < height as usize);
             buffered_stdout.queue(cursor::MoveTo(*x as u16, *y as u16))?;
-            buffered_stdout.queue(style::PrintStyledContent(
-                cell.symbol.with(cell.color).attribute(cell.attr),
-            ))?;
+            match effect.cluster(cell.symbol) {
+                Some(text) => buffered_stdout.queue(style::PrintStyledContent(
+                    text.with(cell.color).attribute(cell.attr),
+                ))?,
+                None => buffered_stdout.queue(style::PrintStyledContent(
+                    cell.symbol.with(cell.color).attribute(cell.attr),
+                ))?,
+            };
         }
         buffered_stdout.flush()?;
         effect.update();
@@ -5,7 +5,7 @@ use crate::{
     error::{ConfigError, Result, TartsError},
     life::{ConwayLifeOptions, ConwayLifeOptionsBuilder},
     maze::{MazeOptions, MazeOptionsBuilder},
-    rain::digital_rain::{DigitalRainOptions, DigitalRainOptionsBuilder},
+    rain::digital_rain::DigitalRainOptions,
 };
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
@@ -105,7 +105,7 @@ impl Config {
 
             // Create default config using builders explicitly
             let default_config = Config {
-                matrix: DigitalRainOptionsBuilder::default().build().unwrap(),
+                matrix: DigitalRainOptions::default(),
                 life: ConwayLifeOptionsBuilder::default().build().unwrap(),
                 maze: MazeOptionsBuilder::default().build().unwrap(),
                 boids: BoidsOptionsBuilder::default().build().unwrap(),
@@ -181,7 +181,7 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
-            matrix: DigitalRainOptionsBuilder::default().build().unwrap(),
+            matrix: DigitalRainOptions::default(),
             life: ConwayLifeOptionsBuilder::default().build().unwrap(),
             maze: MazeOptionsBuilder::default().build().unwrap(),
             boids: BoidsOptionsBuilder::default().build().unwrap(),
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub type Result<T> = std::result::Result<T, TartsError>;
 
 #[derive(Debug, thiserror::Error)]
@@ -24,3 +26,70 @@ pub enum ConfigError {
     #[error("Missing required field: {0}")]
     MissingField(String),
 }
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum DigitalRainOptionsError {
+    #[error("Invalid drops range: {0} > {1}")]
+    InvalidDropsRange(u16, u16),
+
+    #[error("Invalid speed range: {0} > {1}")]
+    InvalidSpeedRange(u16, u16),
+
+    #[error("Maximum speed is zero, drops would never move")]
+    ZeroSpeed,
+
+    #[error("Aspect ratio must be positive, got {0}")]
+    InvalidAspectRatio(f32),
+
+    #[error("{0} must be within 0..=1, got {1}")]
+    RateOutOfRange(&'static str, f32),
+
+    #[error("Invalid length range: {0}..={1}, drops are at least 1 long")]
+    InvalidLengthRange(u16, u16),
+
+    #[error("Invalid {0}: {1:?} > {2:?}")]
+    InvalidDurationRange(&'static str, Duration, Duration),
+
+    #[error("Character {0:?} is reserved for grapheme clusters")]
+    ReservedCharacter(char),
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum RainError {
+    #[error("Screen size {0}x{1} has no cells to rain on")]
+    EmptyScreen(u16, u16),
+
+    #[error("Invalid options: {0}")]
+    Options(#[from] DigitalRainOptionsError),
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum CharGroupError {
+    #[error(
+        "Unknown character group: {0:?}, expected one of digits, punctuation, katakana, other"
+    )]
+    Unknown(String),
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum RainDropStyleError {
+    #[error(
+        "Unknown rain drop style: {0:?}, expected one of front, middle, back, fading, gradient, solid"
+    )]
+    Unknown(String),
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum PackedFrameError {
+    #[error("Packed frame ends in the middle of a cell")]
+    UnexpectedEnd,
+
+    #[error("Varint is longer than 32 bits")]
+    VarintOverflow,
+
+    #[error("Invalid glyph code point: {0:#x}")]
+    InvalidGlyph(u32),
+
+    #[error("Invalid named color index: {0}")]
+    InvalidColor(u8),
+}
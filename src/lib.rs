@@ -1,14 +1,29 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
 pub mod blank;
+#[cfg(feature = "std")]
 pub mod boids;
+#[cfg(feature = "std")]
 pub mod buffer;
+#[cfg(feature = "std")]
 pub mod check;
+#[cfg(feature = "std")]
 pub mod common;
+#[cfg(feature = "std")]
 pub mod config;
+#[cfg(feature = "std")]
 pub mod crab;
+#[cfg(feature = "std")]
 pub mod cube;
+#[cfg(feature = "std")]
 pub mod donut;
+#[cfg(feature = "std")]
 pub mod error;
+#[cfg(feature = "std")]
 pub mod life;
+#[cfg(feature = "std")]
 pub mod maze;
+#[cfg(feature = "std")]
 pub mod pipes;
 pub mod rain;
@@ -50,28 +50,13 @@
 //!
 #![cfg(not(test))]
 use crossterm::{self, cursor, execute, terminal};
-// use tarts::{config, rain};
 // use log::info;
-use crate::common::DefaultOptions;
 use std::{io, process};
-
-mod blank;
-mod boids;
-mod buffer;
-mod check;
-mod common;
-mod config;
-mod crab;
-mod cube;
-mod error;
-mod life;
-mod maze;
-mod rain;
-
-mod donut;
-mod pipes;
-
-use crate::config::Config;
+use tarts::common::DefaultOptions;
+use tarts::config::Config;
+use tarts::{
+    blank, boids, check, common, crab, cube, donut, error, life, maze, pipes, rain,
+};
 
 const HELP: &str = "Terminal screensavers, run with arg: matrix, life, maze, boids, cube, crab, donut, pipes";
 const VALID_SAVERS: &[&str] = &[
@@ -153,7 +138,7 @@ fn main() -> Result<(), error::TartsError> {
         let mut guard = TerminalGuard::new()?;
         let (width, height) = terminal::size()?;
 
-        let fps = match args.screen_saver.as_str() {
+        match args.screen_saver.as_str() {
             "matrix" => {
                 // let options = config.get_matrix_options((width, height));
                 let options =
@@ -214,8 +199,7 @@ fn main() -> Result<(), error::TartsError> {
                 );
                 0.0
             }
-        };
-        fps
+        }
     };
 
     println!("Frames per second: {}", fps);
@@ -1,38 +1,676 @@
-use super::draw::{pick_color, pick_style};
+use super::draw::{
+    add_colors, braille_char, braille_dot, downgrade_color, pick_color, pick_style,
+    rotate_hue, scale_color, tint_color,
+};
 use super::gradient;
-use super::rain_drop::RainDrop;
+use super::packed::encode_cells;
+use super::rain_drop::{
+    CharGroup, GRAPHEME_BASE, RainDrop, RainDropStyle, SharedCharSource,
+    default_characters, is_wide,
+};
 use crate::buffer::{Buffer, Cell};
 use crate::common::{DefaultOptions, TerminalEffect};
+use crate::error::{DigitalRainOptionsError, RainError};
 
+use crossterm::style;
 use derive_builder::Builder;
-use rand::{self, Rng};
+use rand::{self, Rng, SeedableRng, rngs::StdRng};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f32::consts::TAU;
+use std::ops::{Range, RangeInclusive};
+use std::time::{Duration, Instant};
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Builder, Default, Debug, PartialEq, Clone, Serialize, Deserialize)]
+/// How overlapping drop heads are composited into one cell
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CollisionMode {
+    /// Slowest drop is drawn on top, others are hidden
+    #[default]
+    ZOrder,
+    /// Colors of colliding heads are summed, clamped at max intensity
+    Additive,
+}
+
+/// Which cell wins where bodies of drops in one column overlap
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SameColumnBlend {
+    /// Slowest drop is drawn on top, as with heads
+    #[default]
+    Topmost,
+    /// Cell with higher intensity is kept whichever drop it belongs to
+    Brightest,
+}
+
+/// How the initial body of a new drop is filled
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InitBody {
+    /// Each cell gets its own random character
+    #[default]
+    Random,
+    /// All cells are copies of one random character
+    RepeatHead,
+}
+
+/// Which end of the drop body is rendered bright
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TailDirection {
+    /// Bright head leads, trail fades behind it
+    #[default]
+    Behind,
+    /// Faded trail leads, bright end is behind
+    Ahead,
+}
+
+/// Fastest speed `adjust_speed` may set, in cells per second
+pub const MAX_SPEED: u16 = 200;
+
+/// Speed range used when none is set, in cells per second
+const DEFAULT_SPEED_RANGE: (u16, u16) = (2, 16);
+
+/// Drops range used when options do not set one
+const DEFAULT_DROPS_RANGE: (u16, u16) = (10, 20);
+
+/// Longest tick `fast_forward` splits time into
+const FAST_FORWARD_STEP: Duration = Duration::from_millis(100);
+
+/// Number of recent ticks averaged by `average_fps_hint`
+const TICK_COST_WINDOW: usize = 30;
+
+/// Lengths of drops spawned by `burst`
+const BURST_LENGTH: RangeInclusive<usize> = 3..=6;
+
+/// Color of head character in the frame it was added with `new_char_flash`
+const FLASH_COLOR: style::Color = style::Color::Rgb {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+
+/// Extra weight a character group gets at its peak of pool rotation
+const ROTATION_PEAK_WEIGHT: u32 = 8;
+
+/// Number of distinct group mixes over one period of pool rotation,
+/// the weighted pool is rebuilt only when the mix changes
+const ROTATION_STEPS: u32 = 32;
+
+/// Color of burn-in marks
+const BURN_IN_COLOR: style::Color = style::Color::Rgb { r: 0, g: 60, b: 0 };
+
+/// Hidden message appearing in cells drops pass through
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevealOptions {
+    /// Message, lines are split by `\n`, spaces stay transparent
+    pub text: String,
+    /// Top left cell of the message
+    pub anchor: (u16, u16),
+    /// Chance for a message cell to lock each tick a drop passes it
+    pub freeze_probability: f32,
+}
+
+/// Colors terminal is able to show
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ColorSupport {
+    /// Any RGB color
+    #[default]
+    TrueColor,
+    /// 256 colors palette
+    Ansi256,
+    /// Basic 16 colors
+    Ansi16,
+}
+
+/// Guess terminal color support from `$COLORTERM` and `$TERM`
+pub fn detect_color_support() -> ColorSupport {
+    color_support_from_env(
+        std::env::var("COLORTERM").ok().as_deref(),
+        std::env::var("TERM").ok().as_deref(),
+    )
+}
+
+fn color_support_from_env(
+    colorterm: Option<&str>,
+    term: Option<&str>,
+) -> ColorSupport {
+    if matches!(colorterm, Some("truecolor" | "24bit")) {
+        ColorSupport::TrueColor
+    } else if term.is_some_and(|term| term.contains("256color")) {
+        ColorSupport::Ansi256
+    } else {
+        ColorSupport::Ansi16
+    }
+}
+
+/// How fractional drop position is turned into a screen row
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Rounding {
+    /// Nearest row, drop moves to the next row half way through
+    #[default]
+    Round,
+    /// Row drop is currently in
+    Floor,
+    /// Row drop is moving into
+    Ceil,
+}
+
+impl Rounding {
+    #[inline]
+    pub fn apply(self, value: f32) -> f32 {
+        match self {
+            Rounding::Round => value.round(),
+            Rounding::Floor => value.floor(),
+            Rounding::Ceil => value.ceil(),
+        }
+    }
+}
+
+/// What happens to drops pushed sideways past the screen edge
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HorizontalEdge {
+    /// Drop stays at the edge column
+    #[default]
+    Clamp,
+    /// Drop comes out from the other side of the screen
+    Wrap,
+}
+
+impl HorizontalEdge {
+    /// Fit column `x` into the screen `width`
+    #[inline]
+    pub fn apply(self, x: f32, width: u16) -> u16 {
+        let width = width.max(1);
+        match self {
+            HorizontalEdge::Clamp => x.clamp(0.0, (width - 1) as f32) as u16,
+            HorizontalEdge::Wrap => (x as i32).rem_euclid(width as i32) as u16,
+        }
+    }
+}
+
+/// Where across the screen width drops spawn more often
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ColumnBias {
+    /// Every column has equal chance
+    #[default]
+    Uniform,
+    /// Columns near the middle are more likely
+    Center,
+    /// Columns near left and right edges are more likely
+    Edges,
+}
+
+impl ColumnBias {
+    /// Random position in `0.0..1.0` across the width shaped by the bias
+    pub fn sample<R: Rng + ?Sized>(self, rng: &mut R) -> f32 {
+        let pos = match self {
+            ColumnBias::Uniform => rng.random::<f32>(),
+            // mean of two uniform values peaks in the middle
+            ColumnBias::Center => (rng.random::<f32>() + rng.random::<f32>()) / 2.0,
+            // same peak shifted by half of the width to the edges
+            ColumnBias::Edges => {
+                ((rng.random::<f32>() + rng.random::<f32>()) / 2.0 + 0.5) % 1.0
+            }
+        };
+        pos.clamp(0.0, 1.0 - f32::EPSILON)
+    }
+
+    /// Pick one of the `columns` with the bias
+    pub fn pick<R: Rng + ?Sized>(self, columns: &[u16], rng: &mut R) -> u16 {
+        let index = (self.sample(rng) * columns.len() as f32) as usize;
+        columns[index.min(columns.len() - 1)]
+    }
+}
+
+/// Sine path drops follow around their column
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WiggleOptions {
+    /// Largest shift from the column, in rows. Converted to columns
+    /// with `DigitalRainOptions::aspect_ratio`
+    pub amplitude: f32,
+    /// Angular frequency per row fallen
+    pub frequency: f32,
+}
+
+/// Emphasis of a hero drop, see `DigitalRain::mark_hero`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeroStyle {
+    /// Color all cells of the drop are tinted with
+    pub color: gradient::Color,
+    /// Brightness multiplier, values above 1.0 make the drop stand out
+    pub brightness: f32,
+}
+
+impl Default for HeroStyle {
+    fn default() -> Self {
+        Self {
+            color: gradient::Color {
+                r: 180,
+                g: 255,
+                b: 220,
+            },
+            brightness: 1.5,
+        }
+    }
+}
+
+/// Presets of character set and colors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Theme {
+    /// Falling zeroes and ones
+    Binary,
+    /// Falling hexadecimal digits
+    Hex,
+}
+
+impl Theme {
+    pub fn characters(&self) -> Vec<char> {
+        match self {
+            Theme::Binary => vec!['0', '1'],
+            Theme::Hex => ('0'..='9').chain('a'..='f').collect(),
+        }
+    }
+
+    pub fn color(&self) -> gradient::Color {
+        gradient::Color { r: 0, g: 255, b: 0 }
+    }
+}
+
+#[derive(Builder, Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[builder(build_fn(validate = "Self::validate_speed"))]
 pub struct DigitalRainOptions {
+    #[builder(default = "DEFAULT_DROPS_RANGE")]
     pub drops_range: (u16, u16),
+    /// Slowest and fastest drop speed, in cells per second
+    #[builder(default = "DEFAULT_SPEED_RANGE")]
     pub speed_range: (u16, u16),
+    #[builder(default)]
+    #[serde(default)]
+    pub collision_mode: CollisionMode,
+    /// Height to width ratio of a terminal cell, usually about 2.0.
+    /// Distances given in rows which are laid out sideways, wiggle sway
+    /// and splash reach, are converted to columns with it
+    #[builder(default = "default_aspect_ratio()")]
+    #[serde(default = "default_aspect_ratio")]
+    pub aspect_ratio: f32,
+    /// Minimum number of screen columns per drop, caps drops count on
+    /// narrow terminals. Zero means no cap
+    #[builder(default)]
+    #[serde(default)]
+    pub min_columns_per_drop: u16,
+    /// Fraction of screen columns left without drops, picked on construction
+    #[builder(default)]
+    #[serde(default)]
+    pub dead_column_rate: f32,
+    /// Splash dim glyphs to the sides when a head reaches the bottom row,
+    /// as far as one row converted to columns with `aspect_ratio`
+    #[builder(default)]
+    #[serde(default)]
+    pub splash: bool,
+    /// How spawned drops fill their initial body, random characters
+    /// or copies of the head
+    #[builder(default)]
+    #[serde(default)]
+    pub init_body_mode: InitBody,
+    /// Which end of drop bodies is drawn bright, the head or the tail
+    #[builder(default)]
+    #[serde(default)]
+    pub tail_direction: TailDirection,
+    /// Character sets each drop picks one from on spawn, default set if empty
+    #[builder(default)]
+    #[serde(default)]
+    pub per_drop_charsets: Vec<Vec<char>>,
+    /// Longest time step a single update may take, bigger ones are clamped
+    /// so drops do not jump and grow a lot after stalls
+    #[builder(default = "default_max_step()")]
+    #[serde(default = "default_max_step")]
+    pub max_step: Duration,
+    /// Characters drops are made of, default set if empty
+    #[builder(default)]
+    #[serde(default)]
+    pub characters: Vec<char>,
+    /// Single color all cells are tinted with, keeping their brightness
+    #[builder(default)]
+    #[serde(default)]
+    pub monochrome: Option<gradient::Color>,
+    /// Split head brightness between two rows by its fractional position
+    /// for smoother motion
+    #[builder(default)]
+    #[serde(default)]
+    pub antialias_head: bool,
+    /// Flip rendered frame horizontally, simulation is not affected
+    #[builder(default)]
+    #[serde(default)]
+    pub mirror_x: bool,
+    /// Draw every Nth body cell leaving gaps between characters in every
+    /// render path. Drop length and max length still count all rows,
+    /// gaps included
+    #[builder(default = "default_char_spacing()")]
+    #[serde(default = "default_char_spacing")]
+    pub char_spacing: u16,
+    /// Reset drops start with a few characters already on screen,
+    /// so there is no gap while a new drop enters
+    #[builder(default)]
+    #[serde(default)]
+    pub instant_respawn: bool,
+    /// Chance and glyphs for a drop to get rare head on spawn,
+    /// the rest of its body stays regular
+    #[builder(default)]
+    #[serde(default)]
+    pub rare_head: Option<(f32, Vec<char>)>,
+    /// Drops sway along sine path instead of falling straight
+    #[builder(default)]
+    #[serde(default)]
+    pub wiggle: Option<WiggleOptions>,
+    /// Styles drops may get, any style if empty
+    #[builder(default)]
+    #[serde(default)]
+    pub allowed_styles: Vec<RainDropStyle>,
+    /// RGB colors are downgraded to the nearest supported ones on render
+    #[builder(default)]
+    #[serde(default)]
+    pub color_support: ColorSupport,
+    /// Most characters all drops may insert during one tick,
+    /// growth over it is deferred, no limit if `None`
+    #[builder(default)]
+    #[serde(default)]
+    pub max_inserts_per_tick: Option<usize>,
+    /// Characters drops of given style are made of, overrides `characters`
+    #[builder(default)]
+    #[serde(default)]
+    pub style_charsets: HashMap<RainDropStyle, Vec<char>>,
+    /// Fast drops get dim copy of the head drawn right behind it
+    #[builder(default)]
+    #[serde(default)]
+    pub ghost_head: bool,
+    /// How drop positions are mapped to screen rows, in frames, Braille
+    /// and reveal alike. `RainDrop::to_point` and `to_points_vec` always
+    /// round to the nearest row, their `_with` variants take rounding
+    #[builder(default)]
+    #[serde(default)]
+    pub cell_rounding: Rounding,
+    /// All drops in a column fall with the same speed picked by column index
+    #[builder(default)]
+    #[serde(default)]
+    pub per_column_speed: bool,
+    /// Share of drop body cells filled with characters, the rest are
+    /// left empty for fragmented look
+    #[builder(default = "default_unit()")]
+    #[serde(default = "default_unit")]
+    pub body_fill: f32,
+    /// Whether drops pushed past side edges stick to them or wrap around
+    #[builder(default)]
+    #[serde(default)]
+    pub horizontal_edge: HorizontalEdge,
+    /// Message revealed cell by cell by passing drops
+    #[builder(default)]
+    #[serde(default)]
+    pub reveal: Option<RevealOptions>,
+    /// Brightness drop tails fade to, 1.0 keeps drops evenly bright
+    #[builder(default = "default_unit()")]
+    #[serde(default = "default_unit")]
+    pub tail_floor: f32,
+    /// Each drop shifts tail floor by random value up to this one
+    #[builder(default)]
+    #[serde(default)]
+    pub tail_floor_jitter: f32,
+    /// How spawn columns are spread across the screen
+    #[builder(default)]
+    #[serde(default)]
+    pub column_bias: ColumnBias,
+    /// Hue rotation in degrees added per body cell away from the head
+    #[builder(default)]
+    #[serde(default)]
+    pub hue_shift: f32,
+    /// Custom character picker used instead of the character sets,
+    /// not serialized
+    #[builder(default)]
+    #[serde(skip)]
+    pub char_source: Option<SharedCharSource>,
+    /// Rows above the screen reset drops start from, so heads enter
+    /// already moving with body grown behind them
+    #[builder(default)]
+    #[serde(default)]
+    pub preroll_rows: u16,
+    /// Chance for a cell passed by a head to keep faint mark of it
+    /// until `DigitalRain::clear_burn_in`
+    #[builder(default)]
+    #[serde(default)]
+    pub burn_in_rate: f32,
+    /// Light glyphs deep tail cells are drawn with more and more often
+    /// towards the tail end
+    #[builder(default)]
+    #[serde(default)]
+    pub tail_light_chars: Option<Vec<char>>,
+    /// Drops created on start wait random time from this range
+    /// before they begin falling
+    #[builder(default = "(Duration::ZERO, Duration::ZERO)")]
+    #[serde(default)]
+    pub start_delay_range: (Duration, Duration),
+    /// Grapheme clusters drops are made of instead of character sets,
+    /// for emoji and other glyphs of several code points. Bodies and
+    /// rendered cells keep them as `GRAPHEME_BASE` based codes, which
+    /// `TerminalEffect::cluster` turns back into clusters when drawing
+    #[builder(default)]
+    #[serde(default)]
+    pub graphemes: Vec<String>,
+    /// Groups default character set is made of, all groups if empty
+    #[builder(default)]
+    #[serde(default)]
+    pub char_groups: Vec<CharGroup>,
+    /// Leave out double width glyphs which break terminal layout
+    #[builder(default)]
+    #[serde(default)]
+    pub narrow_only: bool,
+    /// Each drop swaps one of its characters with interval picked from
+    /// this range on spawn, so drops shimmer on their own cadence
+    #[builder(default)]
+    #[serde(default)]
+    pub flicker_interval_range: Option<(Duration, Duration)>,
+    /// Spread heads of drops near the top with low discrepancy sequence
+    /// instead of random rows, and queue respawned drops above the screen
+    /// the same way, so heads do not move in waves
+    #[builder(default)]
+    #[serde(default)]
+    pub stagger: bool,
+    /// Each drop is made of this many glyphs picked from the pool on spawn,
+    /// for less noisy look
+    #[builder(default)]
+    #[serde(default)]
+    pub glyphs_per_drop: Option<usize>,
+    /// Brightness multiplier of the whole rain, lower values leave it
+    /// faint behind other content
+    #[builder(default = "default_unit()")]
+    #[serde(default = "default_unit")]
+    pub global_opacity: f32,
+    /// Chance for a drop to fade out completely somewhere mid-screen
+    /// after random lifespan and start over
+    #[builder(default)]
+    #[serde(default)]
+    pub ephemeral_rate: f32,
+    /// Shortest and longest drop length, picked by screen height if `None`
+    #[builder(default)]
+    #[serde(default)]
+    pub length_range: Option<(u16, u16)>,
+    /// Scale head brightness by drop speed relative to max speed
+    #[builder(default)]
+    #[serde(default)]
+    pub speed_brightness: bool,
+    /// Characters for cells on screen rows in range, first matching
+    /// region wins over other character sets
+    #[builder(default)]
+    #[serde(default)]
+    pub region_charsets: Vec<(Range<u16>, Vec<char>)>,
+    /// Draw only heads of drops for sparse falling stars look
+    #[builder(default)]
+    #[serde(default)]
+    pub heads_only: bool,
+    /// Colors spawned drops take in turn, each drop keeps its color
+    #[builder(default)]
+    #[serde(default)]
+    pub palette_cycle: Option<Vec<gradient::Color>>,
+    /// Fraction of screen height at the bottom where drops slow down
+    /// for a softer landing, 0.0 disables easing
+    #[builder(default)]
+    #[serde(default)]
+    pub ease_out_zone: f32,
+    /// Dim tails cut by the top edge so they dissolve instead of clipping
+    #[builder(default)]
+    #[serde(default)]
+    pub top_fade: bool,
+    /// Spawn drops only in every n-th column for an orderly look
+    #[builder(default)]
+    #[serde(default)]
+    pub lane_spacing: Option<u16>,
+    /// Spawn drops with random part of body already in place, otherwise
+    /// drops start with the head only and emerge by growing
+    #[builder(default = "default_prefill()")]
+    #[serde(default = "default_prefill")]
+    pub prefill: bool,
+    /// How overlapping bodies of drops in one column are composited
+    #[builder(default)]
+    #[serde(default)]
+    pub same_column_blend: SameColumnBlend,
+    /// Time new and reset drops take to ramp up from dark to full
+    /// brightness, zero shows them at once
+    #[builder(default)]
+    #[serde(default)]
+    pub spawn_fade: Duration,
+    /// Flash head character white for one frame right after it was added
+    #[builder(default)]
+    #[serde(default)]
+    pub new_char_flash: bool,
+    /// How often characters of each group appear in default character set,
+    /// groups which are not listed have weight 1
+    #[builder(default)]
+    #[serde(default)]
+    pub group_weights: HashMap<CharGroup, u32>,
+    /// Period over which group weights drift so each group in turn
+    /// dominates the default set, `group_weights` are ignored meanwhile
+    #[builder(default)]
+    #[serde(default)]
+    pub pool_rotation: Option<Duration>,
+}
+
+fn default_aspect_ratio() -> f32 {
+    2.0
+}
+
+fn default_max_step() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_char_spacing() -> u16 {
+    1
+}
+
+fn default_unit() -> f32 {
+    1.0
+}
+
+fn default_prefill() -> bool {
+    true
+}
+
+impl DigitalRainOptionsBuilder {
+    /// Reject speed range which never lets drops move
+    fn validate_speed(&self) -> Result<(), String> {
+        match self.speed_range {
+            Some((_, 0)) => Err(DigitalRainOptionsError::ZeroSpeed.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set character set and colors from preset
+    pub fn theme(&mut self, theme: Theme) -> &mut Self {
+        self.characters = Some(theme.characters());
+        self.monochrome = Some(Some(theme.color()));
+        self
+    }
+
+    /// Split text into grapheme clusters drops are made of
+    #[cfg(feature = "graphemes")]
+    pub fn grapheme_text(&mut self, text: &str) -> &mut Self {
+        self.graphemes = Some(text.graphemes(true).map(String::from).collect());
+        self
+    }
+
+    /// Set slowest drop speed in cells (rows) per second,
+    /// same as the first value of `speed_range`
+    pub fn min_speed_cells_per_sec(&mut self, speed: u16) -> &mut Self {
+        let (_, max) = self.speed_range.unwrap_or(DEFAULT_SPEED_RANGE);
+        self.speed_range = Some((speed, max));
+        self
+    }
+
+    /// Set fastest drop speed in cells (rows) per second,
+    /// same as the second value of `speed_range`
+    pub fn max_speed_cells_per_sec(&mut self, speed: u16) -> &mut Self {
+        let (min, _) = self.speed_range.unwrap_or(DEFAULT_SPEED_RANGE);
+        self.speed_range = Some((min, speed));
+        self
+    }
 }
 
+/// Partial options, fields which are set overwrite ones of
+/// `DigitalRainOptions::apply` receiver. Fields are set with builder setters
+pub type OptionsPatch = DigitalRainOptionsBuilder;
+
 pub struct DigitalRain {
     pub screen_size: (u16, u16),
     options: DigitalRainOptions,
     gradients: Vec<Vec<gradient::Color>>,
     rain_drops: Vec<RainDrop>,
     buffer: Buffer,
-    rng: rand::prelude::ThreadRng,
+    rng: StdRng,
+    /// Columns where drops are allowed to spawn
+    columns: Vec<u16>,
+    /// Columns where drops are paused
+    frozen_columns: HashSet<u16>,
+    /// Splash cells visible for one frame
+    splash_cells: Vec<(u16, u16)>,
+    /// Id given to the next spawned drop
+    next_drop_id: usize,
+    /// Called with drop id each time a drop is reset
+    on_reset: Option<Box<dyn FnMut(usize)>>,
+    /// Counters collected since construction
+    stats: LifetimeStats,
+    /// Message cells as x, y, glyph and whether it is locked already
+    reveal_cells: Vec<(u16, u16, char, bool)>,
+    /// Faint glyphs left by passing heads, drawn beneath the drops
+    burn_in: HashMap<(u16, u16), char>,
+    /// Screen cells rain is never drawn over, row by row
+    obstacles: Vec<bool>,
+    /// Row width of `obstacles`
+    obstacles_width: u16,
+    /// Time the latest ticks took, oldest first
+    tick_costs: VecDeque<Duration>,
+    /// Time into the current period of character pool rotation
+    pool_elapsed: Duration,
+    /// Step of pool rotation and default characters weighted for it,
+    /// `None` without rotation
+    rotated_pool: Option<(u32, Vec<char>)>,
+}
+
+/// Counters of the whole rain life
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LifetimeStats {
+    /// Number of drops which passed the screen and started over
+    pub resets: u64,
+    /// Rows passed by all drops together
+    pub distance: f64,
+}
+
+/// Positions, speeds, styles and bodies of all drops at some moment
+#[derive(Clone)]
+pub struct RainSnapshot {
+    rain_drops: Vec<RainDrop>,
 }
 
 impl TerminalEffect for DigitalRain {
     /// Calculate difference between current frame and previous frame
     fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
-        let mut curr_buffer =
-            Buffer::new(self.screen_size.0 as usize, self.screen_size.1 as usize);
-
-        // fill current buffer
-        // first draw drops with bigger fy
-        Self::fill_buffer(&mut self.rain_drops, &mut curr_buffer, &self.gradients);
+        let curr_buffer = self.render();
 
         let diff = self.buffer.diff(&curr_buffer);
         self.buffer = curr_buffer;
@@ -41,45 +679,118 @@ impl TerminalEffect for DigitalRain {
 
     /// Update each rain drop position
     fn update(&mut self) {
-        for rain_drop in self.rain_drops.iter_mut() {
-            rain_drop.update(
-                self.screen_size,
-                &self.options,
-                Duration::from_millis(50),
-                &mut self.rng,
-            );
-        }
-
-        self.add_one();
+        self.tick(Duration::from_millis(50));
     }
 
     fn update_size(&mut self, width: u16, height: u16) {
+        self.fit_columns(width);
+        self.crop_layers((width, height));
         self.screen_size = (width, height);
     }
 
+    fn cluster(&self, symbol: char) -> Option<&str> {
+        self.options.cluster(symbol)
+    }
+
     fn reset(&mut self) {
-        let new_effect = DigitalRain::new(self.options.clone(), self.screen_size);
+        let mut new_effect = DigitalRain::with_rng(
+            self.options.clone(),
+            self.screen_size,
+            self.rng.clone(),
+        );
+        new_effect.on_reset = self.on_reset.take();
         *self = new_effect;
     }
 }
 
+/// Clone is a fork of the animation: drops, options and random generator
+/// state are copied, so ticking both with the same steps gives the same
+/// frames. Reset callback is not cloned.
+impl Clone for DigitalRain {
+    fn clone(&self) -> Self {
+        Self {
+            screen_size: self.screen_size,
+            options: self.options.clone(),
+            gradients: self.gradients.clone(),
+            rain_drops: self.rain_drops.clone(),
+            buffer: self.buffer.clone(),
+            rng: self.rng.clone(),
+            columns: self.columns.clone(),
+            frozen_columns: self.frozen_columns.clone(),
+            splash_cells: self.splash_cells.clone(),
+            next_drop_id: self.next_drop_id,
+            on_reset: None,
+            stats: self.stats,
+            reveal_cells: self.reveal_cells.clone(),
+            burn_in: self.burn_in.clone(),
+            obstacles: self.obstacles.clone(),
+            obstacles_width: self.obstacles_width,
+            tick_costs: self.tick_costs.clone(),
+            pool_elapsed: self.pool_elapsed,
+            rotated_pool: self.rotated_pool.clone(),
+        }
+    }
+}
+
 /// Process digital rain effect.
 /// Noice that all processing done implying coordinates started from 0, 0
 /// and width / height is actual number of columnts and rows
 impl DigitalRain {
     // Initialize screensaver
     pub fn new(options: DigitalRainOptions, screen_size: (u16, u16)) -> Self {
-        let mut rng = rand::rng();
-        let mut rain_drops: Vec<RainDrop> = vec![];
-        let mut buffer: Buffer =
-            Buffer::new(screen_size.0 as usize, screen_size.1 as usize);
-        for rain_drop_id in 1..=options.get_min_drops_number() {
-            rain_drops.push(RainDrop::new(
-                screen_size,
-                &options,
-                rain_drop_id as usize,
-                &mut rng,
-            ));
+        Self::try_new(options, screen_size).unwrap()
+    }
+
+    /// Initialize screensaver checking size and options up front,
+    /// errors are returned instead of panics deep inside of drops
+    pub fn try_new(
+        options: DigitalRainOptions,
+        screen_size: (u16, u16),
+    ) -> Result<Self, RainError> {
+        let (width, height) = screen_size;
+        if width == 0 || height == 0 {
+            return Err(RainError::EmptyScreen(width, height));
+        }
+        options.validate()?;
+        Ok(Self::with_rng(
+            options,
+            screen_size,
+            StdRng::from_rng(&mut rand::rng()),
+        ))
+    }
+
+    /// Initialize screensaver with fixed seed, rains created with the same
+    /// seed, options and size play exactly the same animation
+    pub fn new_seeded(
+        options: DigitalRainOptions,
+        screen_size: (u16, u16),
+        seed: u64,
+    ) -> Self {
+        Self::with_rng(options, screen_size, StdRng::seed_from_u64(seed))
+    }
+
+    /// Start the animation over from `seed` keeping options and size,
+    /// all drops get fresh random states
+    pub fn reseed(&mut self, seed: u64) {
+        let mut new_effect =
+            DigitalRain::new_seeded(self.options.clone(), self.screen_size, seed);
+        new_effect.on_reset = self.on_reset.take();
+        *self = new_effect;
+    }
+
+    fn with_rng(
+        options: DigitalRainOptions,
+        screen_size: (u16, u16),
+        mut rng: StdRng,
+    ) -> Self {
+        // pick columns allowed for spawn, at least one is kept alive
+        let dead_column_rate = options.dead_column_rate.clamp(0.0, 1.0) as f64;
+        let mut columns: Vec<u16> = (0..screen_size.0)
+            .filter(|x| options.is_lane(*x))
+            .filter(|_| !rng.random_bool(dead_column_rate))
+            .collect();
+        if columns.is_empty() {
+            columns.push(options.snap_to_lane(rng.random_range(0..screen_size.0)));
         }
 
         // fill gradients
@@ -131,131 +842,2850 @@ impl DigitalRain {
             ),
         ];
 
-        Self::fill_buffer(&mut rain_drops, &mut buffer, &gradients);
-
-        Self {
+        let mut digital_rain = Self {
             screen_size,
             options,
             gradients,
-            rain_drops,
-            buffer,
+            rain_drops: vec![],
+            buffer: Buffer::new(screen_size.0 as usize, screen_size.1 as usize),
             rng,
+            columns,
+            frozen_columns: HashSet::new(),
+            splash_cells: vec![],
+            next_drop_id: 1,
+            on_reset: None,
+            stats: LifetimeStats::default(),
+            reveal_cells: vec![],
+            burn_in: HashMap::new(),
+            obstacles: vec![],
+            obstacles_width: 0,
+            tick_costs: VecDeque::with_capacity(TICK_COST_WINDOW),
+            pool_elapsed: Duration::ZERO,
+            rotated_pool: None,
+        };
+        if let Some(reveal) = digital_rain.options.reveal.as_ref() {
+            let (ax, ay) = reveal.anchor;
+            for (dy, line) in reveal.text.lines().enumerate() {
+                for (dx, glyph) in line.chars().enumerate() {
+                    let (x, y) = (ax as usize + dx, ay as usize + dy);
+                    if glyph != ' '
+                        && x < screen_size.0 as usize
+                        && y < screen_size.1 as usize
+                    {
+                        digital_rain
+                            .reveal_cells
+                            .push((x as u16, y as u16, glyph, false));
+                    }
+                }
+            }
+        }
+        let drops_number = digital_rain
+            .options
+            .get_min_drops_number()
+            .min(digital_rain.options.get_drops_cap(screen_size.0));
+        digital_rain.rotate_pool(Duration::ZERO);
+        for _ in 0..drops_number {
+            let rain_drop =
+                digital_rain.using_own_rng(|rain, rng| rain.new_drop(rng));
+            digital_rain.rain_drops.push(rain_drop);
         }
+        digital_rain.buffer = digital_rain.render();
+        digital_rain
     }
 
-    pub fn fill_buffer(
-        rain_drops: &mut [RainDrop],
-        buffer: &mut Buffer,
-        gradients: &[Vec<gradient::Color>],
-    ) {
-        rain_drops.sort_by(|a, b| a.speed.partial_cmp(&b.speed).unwrap());
-        for rain_drop in rain_drops.iter().rev() {
-            let points = rain_drop.to_points_vec();
-            for (index, (x, y, character)) in points.iter().enumerate() {
-                let (width, height) = buffer.get_size();
-                if *x < width as u16 && *y < height as u16 {
-                    buffer.set(
-                        *x as usize,
-                        *y as usize,
-                        Cell::new(
-                            *character,
-                            pick_color(&rain_drop.style, index, gradients),
-                            pick_style(&rain_drop.style, index),
-                        ),
-                    );
-                };
+    /// Advance all rain drops by `dt`
+    pub fn tick(&mut self, dt: Duration) {
+        self.using_own_rng(|rain, rng| rain.tick_with_rng(dt, rng));
+    }
+
+    /// Same as `tick` but random values are drawn from caller's `rng`,
+    /// internal one is left untouched
+    pub fn tick_with_rng(&mut self, dt: Duration, rng: &mut impl Rng) {
+        let started = Instant::now();
+        self.rotate_pool(dt);
+        self.update_all(dt, rng);
+        self.lock_reveal_cells(rng);
+        self.spawn_one(rng);
+        self.record_tick_cost(started.elapsed());
+    }
+
+    /// Move character pool rotation by `dt` and rebuild the weighted pool
+    /// once the rotation gets to the next step
+    fn rotate_pool(&mut self, dt: Duration) {
+        let period = match self.options.pool_rotation {
+            Some(period) if !period.is_zero() => period,
+            _ => {
+                self.rotated_pool = None;
+                return;
             }
+        };
+        let elapsed = (self.pool_elapsed + dt).as_nanos() % period.as_nanos();
+        self.pool_elapsed = Duration::from_nanos(elapsed as u64);
+        let phase = self.pool_elapsed.as_secs_f32() / period.as_secs_f32();
+        let step = (phase * ROTATION_STEPS as f32) as u32 % ROTATION_STEPS;
+        if matches!(&self.rotated_pool, Some((current, _)) if *current == step) {
+            return;
         }
+        let weights = rotation_weights(step as f32 / ROTATION_STEPS as f32);
+        let pool = self.options.weighted_pool(&weights).into_owned();
+        self.rotated_pool = Some((step, pool));
     }
 
-    /// Add one more worm with decent chance
-    pub fn add_one(&mut self) {
-        if self.rain_drops.len() >= self.options.get_max_drops_number() as usize {
+    /// Remember time one tick took, only last `TICK_COST_WINDOW` ones
+    /// are kept. Ticks record own cost, hosts may feed frame costs too
+    pub fn record_tick_cost(&mut self, cost: Duration) {
+        if self.tick_costs.len() == TICK_COST_WINDOW {
+            self.tick_costs.pop_front();
+        }
+        self.tick_costs.push_back(cost);
+    }
+
+    /// Frames per second rain could keep up given average cost of recent
+    /// ticks, `None` until a tick with measurable cost happened
+    pub fn average_fps_hint(&self) -> Option<f32> {
+        let total: Duration = self.tick_costs.iter().sum();
+        if total.is_zero() {
+            return None;
+        }
+        Some(self.tick_costs.len() as f32 / total.as_secs_f32())
+    }
+
+    /// Run `f` with internal rng taken out of `self` for the call
+    fn using_own_rng<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self, &mut StdRng) -> T,
+    ) -> T {
+        // placeholder is never drawn from, real rng is put back below
+        let mut rng = std::mem::replace(&mut self.rng, StdRng::from_seed([0; 32]));
+        let result = f(self, &mut rng);
+        self.rng = rng;
+        result
+    }
+
+    /// Move, grow and reset all drops in one pass, fields are borrowed
+    /// once for the whole pass instead of per drop
+    fn update_all(&mut self, dt: Duration, rng: &mut impl Rng) {
+        let Self {
+            screen_size,
+            options,
+            rain_drops,
+            columns,
+            frozen_columns,
+            splash_cells,
+            on_reset,
+            stats,
+            burn_in,
+            rotated_pool,
+            ..
+        } = self;
+        let bottom = screen_size.1.saturating_sub(1);
+        let rounding = options.cell_rounding;
+        let step = dt.min(options.max_step);
+        let mut budget = options.max_inserts_per_tick.unwrap_or(usize::MAX);
+        let burn_in_rate = options.burn_in_rate.clamp(0.0, 1.0) as f64;
+        // splash is one row wide, cells are narrower than tall
+        let splash_reach = (options.aspect_ratio.round() as u16).max(1);
+        splash_cells.clear();
+        let mut exited = vec![];
+
+        for rain_drop in rain_drops.iter_mut() {
+            if frozen_columns.contains(&rain_drop.base_fx) {
+                continue;
+            }
+            let (_, prev_head_y) = rain_drop.to_point_with(rounding);
+            if !rain_drop.is_waiting() {
+                stats.distance += rain_drop.vertical_advance(step) as f64;
+            }
+            if rain_drop.update_with_budget(
+                *screen_size,
+                options,
+                dt,
+                &mut budget,
+                rng,
+            ) {
+                if rain_drop.burst {
+                    exited.push(rain_drop.id());
+                    continue;
+                }
+                let x = options.column_bias.pick(columns, rng);
+                rain_drop.set_column(x);
+                if options.per_column_speed {
+                    rain_drop.speed = options.column_speed(x);
+                }
+                if let Some((_, pool)) = rotated_pool.as_ref() {
+                    rain_drop.pick_glyphs_from(options, pool, rng);
+                }
+                if let Some(on_reset) = on_reset.as_mut() {
+                    on_reset(rain_drop.id());
+                }
+                stats.resets += 1;
+                continue;
+            }
+            let (head_x, head_y) = rain_drop.to_point_with(rounding);
+            if burn_in_rate > 0.0
+                && head_x < screen_size.0
+                && (0..screen_size.1 as i32).contains(&rain_drop.head_row(rounding))
+                && let Some(glyph) = rain_drop.body.first()
+                && rng.random_bool(burn_in_rate)
+            {
+                burn_in.insert((head_x, head_y), *glyph);
+            }
+            if options.splash && prev_head_y < bottom && head_y >= bottom {
+                for offset in 1..=splash_reach {
+                    // drops left past the right edge by resize may splash too
+                    if let Some(x) = head_x.checked_sub(offset)
+                        && x < screen_size.0
+                    {
+                        splash_cells.push((x, bottom));
+                    }
+                    if head_x + offset < screen_size.0 {
+                        splash_cells.push((head_x + offset, bottom));
+                    }
+                }
+            }
+        }
+        if !exited.is_empty() {
+            rain_drops.retain(|rain_drop| !exited.contains(&rain_drop.id()));
+        }
+    }
+
+    /// Lock message cells which drops pass through with given chance
+    fn lock_reveal_cells(&mut self, rng: &mut impl Rng) {
+        let Some(reveal) = self.options.reveal.as_ref() else {
             return;
         };
-        let mut rng = rand::rng();
-        if rng.random_range(0.0..=1.0) <= 0.3 {
-            self.rain_drops.push(RainDrop::new(
-                self.screen_size,
-                &self.options,
-                self.rain_drops.len() + 1,
-                &mut rng,
-            ));
-        };
+        if self.is_revealed() {
+            return;
+        }
+        let chance = reveal.freeze_probability.clamp(0.0, 1.0) as f64;
+        // cells under drops are collected once instead of per message cell
+        let covered: HashSet<(u16, u16)> = self
+            .rain_drops
+            .iter()
+            .flat_map(|rain_drop| rain_drop.to_points(&self.options))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        for (x, y, _, locked) in self.reveal_cells.iter_mut() {
+            if *locked {
+                continue;
+            }
+            if covered.contains(&(*x, *y)) && rng.random_bool(chance) {
+                *locked = true;
+            }
+        }
     }
-}
 
-impl DigitalRainOptions {
-    #[inline]
-    pub fn get_min_drops_number(&self) -> u16 {
-        self.drops_range.0
+    /// Whether every cell of the revealed message is locked
+    pub fn is_revealed(&self) -> bool {
+        self.reveal_cells.iter().all(|(.., locked)| *locked)
     }
 
-    #[inline]
-    pub fn get_max_drops_number(&self) -> u16 {
-        self.drops_range.1
+    /// Screen is full when drops number reached its maximum and
+    /// drops already passed from top to bottom
+    pub fn is_full(&self) -> bool {
+        let max_drops = self
+            .options
+            .get_max_drops_number()
+            .min(self.options.get_drops_cap(self.screen_size.0));
+        self.stats.resets > 0 && self.rain_drops.len() >= max_drops as usize
     }
 
-    #[inline]
-    pub fn get_min_speed(&self) -> u16 {
-        self.speed_range.0
+    /// Resets and distance passed by drops since rain was created
+    pub fn lifetime_stats(&self) -> LifetimeStats {
+        self.stats
     }
 
-    #[inline]
-    pub fn get_max_speed(&self) -> u16 {
-        self.speed_range.1
+    /// Advance simulation by `total` time without rendering, time is split
+    /// into `FAST_FORWARD_STEP` ticks so drops grow as in real time
+    pub fn fast_forward(&mut self, total: Duration) {
+        let mut left = total;
+        while left >= FAST_FORWARD_STEP {
+            self.tick(FAST_FORWARD_STEP);
+            left -= FAST_FORWARD_STEP;
+        }
+        if !left.is_zero() {
+            self.tick(left);
+        }
     }
-}
 
-impl DefaultOptions for DigitalRain {
-    type Options = DigitalRainOptions;
+    /// Tick until screen is full, but no more than `max_ticks` times,
+    /// returns number of ticks taken
+    pub fn tick_until_steady(&mut self, dt: Duration, max_ticks: usize) -> usize {
+        let mut ticks = 0;
+        while ticks < max_ticks && !self.is_full() {
+            self.tick(dt);
+            ticks += 1;
+        }
+        ticks
+    }
 
-    fn default_options(width: u16, height: u16) -> Self::Options {
-        let drops_range = {
-            let min_drops = (width * height) / 160; // Approximately 0.6% of screen space
-            let max_drops = (width * height) / 80; // Approximately 1.2% of screen space
-            (min_drops.max(10), max_drops.max(20)) // Ensure minimum values
-        };
+    /// Composite all rain drops into a new buffer of screen size
+    pub fn render(&self) -> Buffer {
+        let mut buffer =
+            Buffer::new(self.screen_size.0 as usize, self.screen_size.1 as usize);
+        let opacity = self.options.global_opacity.clamp(0.0, 1.0);
+        // burn-in marks are the lowest layer
+        for ((x, y), glyph) in self.burn_in.iter() {
+            let x = match self.options.mirror_x {
+                true => self.screen_size.0 - 1 - x,
+                false => *x,
+            };
+            buffer.set(
+                x as usize,
+                *y as usize,
+                Cell::new(
+                    *glyph,
+                    scale_color(BURN_IN_COLOR, opacity),
+                    style::Attribute::NormalIntensity,
+                ),
+            );
+        }
+        // splashes stay beneath the drops
+        for (x, y) in self.splash_cells.iter() {
+            let x = match self.options.mirror_x {
+                true => self.screen_size.0 - 1 - x,
+                false => *x,
+            };
+            buffer.set(
+                x as usize,
+                *y as usize,
+                Cell::new(
+                    '.',
+                    scale_color(style::Color::DarkGreen, opacity),
+                    style::Attribute::NormalIntensity,
+                ),
+            );
+        }
+        // first draw drops with bigger fy
+        Self::fill_buffer(
+            &self.rain_drops,
+            &mut buffer,
+            &self.gradients,
+            &self.options,
+        );
+        // locked message cells stay on top of the rain
+        let color = pick_color(&RainDropStyle::Front, 0, &self.gradients);
+        for (x, y, glyph, _) in self.reveal_cells.iter().filter(|c| c.3) {
+            let x = match self.options.mirror_x {
+                true => self.screen_size.0 - 1 - x,
+                false => *x,
+            };
+            let color = downgrade_color(
+                scale_color(color, opacity),
+                self.options.color_support,
+            );
+            buffer.set(
+                x as usize,
+                *y as usize,
+                Cell::new(*glyph, color, style::Attribute::Bold),
+            );
+        }
+        // obstacle cells are left for the host to draw
+        let width = self.obstacles_width as usize;
+        for (index, _) in self.obstacles.iter().enumerate().filter(|c| *c.1) {
+            let (x, y) = (index % width, index / width);
+            if x < buffer.width && y < buffer.height {
+                buffer.set(x, y, Cell::default());
+            }
+        }
+        buffer
+    }
 
-        let speed_range = {
-            let min_speed = (height / 20).max(2); // Faster for larger screens
-            let max_speed = (height / 10).max(16); // But not too fast
-            (min_speed, max_speed)
+    /// Cells drawn by every drop in draw order, overlapping cells of
+    /// different drops are all included
+    pub fn frame(&self) -> impl Iterator<Item = (u16, u16, char)> + '_ {
+        let (width, height) = self.screen_size;
+        self.rain_drops
+            .iter()
+            .flat_map(|rain_drop| rain_drop.to_points_styled(&self.options))
+            .filter(move |point| point.x < width && point.y < height)
+            .map(move |point| match self.options.mirror_x {
+                true => (width - 1 - point.x, point.y, point.character),
+                false => (point.x, point.y, point.character),
+            })
+            .filter(|(x, y, _)| !self.is_obstacle(*x, *y))
+    }
+
+    /// Current frame as RGBA pixels row by row, each cell is `cell_px`
+    /// wide and tall block of its color
+    #[cfg(feature = "image")]
+    pub fn render_rgba(&self, cell_px: (u32, u32)) -> Vec<u8> {
+        super::raster::rasterize(&self.render(), cell_px)
+    }
+
+    /// Composited cells of the current state as drawn on the screen,
+    /// one per cell with the top one winning. Nothing is advanced, so it
+    /// is a cheap still image while the animation is paused
+    pub fn paused_frame(&self) -> Vec<(u16, u16, char)> {
+        let buffer = self.render();
+        buffer
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell != Cell::default())
+            .map(|(index, cell)| {
+                let (x, y) = buffer.pos_of(index);
+                (x as u16, y as u16, cell.symbol)
+            })
+            .collect()
+    }
+
+    /// Same as `frame` with characters resolved into drawn text,
+    /// grapheme clusters are kept whole
+    pub fn frame_graphemes(
+        &self,
+    ) -> impl Iterator<Item = (u16, u16, Cow<'_, str>)> + '_ {
+        self.frame()
+            .map(|(x, y, symbol)| (x, y, self.options.glyph(symbol)))
+    }
+
+    /// Set screen cells rain must not be drawn over, `mask` goes row by row
+    /// with `width` cells per row, cells outside of it are free.
+    /// Empty mask removes all obstacles
+    pub fn set_obstacle_mask(&mut self, mask: Vec<bool>, width: u16) {
+        self.obstacles = match width {
+            0 => vec![],
+            _ => mask,
         };
+        self.obstacles_width = width;
+    }
 
-        DigitalRainOptionsBuilder::default()
-            .drops_range(drops_range)
-            .speed_range(speed_range)
-            .build()
-            .unwrap()
+    /// Whether screen cell is covered by obstacle mask
+    fn is_obstacle(&self, x: u16, y: u16) -> bool {
+        x < self.obstacles_width
+            && self
+                .obstacles
+                .get(y as usize * self.obstacles_width as usize + x as usize)
+                .is_some_and(|obstacle| *obstacle)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Number of cells drawn by drops, same as `frame().count()`
+    /// but without allocations
+    pub fn visible_cell_count(&self) -> usize {
+        if self.obstacles.contains(&true) {
+            return self.frame().count();
+        }
+        self.rain_drops
+            .iter()
+            .map(|rain_drop| {
+                rain_drop.visible_cell_count(&self.options, self.screen_size)
+            })
+            .sum()
+    }
 
-    fn get_sane_default_options() -> DigitalRainOptions {
-        DigitalRainOptionsBuilder::default()
-            .drops_range((20, 30))
-            .speed_range((10, 20))
-            .build()
-            .unwrap()
+    /// Capture state of all drops to go back to it later
+    pub fn snapshot(&self) -> RainSnapshot {
+        RainSnapshot {
+            rain_drops: self.rain_drops.clone(),
+        }
     }
 
-    #[test]
-    fn create_new() {
-        let foo = DigitalRain::new(get_sane_default_options(), (100, 100));
-        assert_eq!(foo.rain_drops.len(), 20);
+    /// Put drops back to the captured state
+    pub fn restore(&mut self, snapshot: RainSnapshot) {
+        self.rain_drops = snapshot.rain_drops;
     }
 
-    #[test]
-    fn no_diff() {
-        let mut foo = DigitalRain::new(get_sane_default_options(), (100, 100));
-        let q = foo.get_diff();
+    /// Scale speed of all drops and speed range by `factor`,
+    /// speeds are kept within `1..=MAX_SPEED`
+    pub fn adjust_speed(&mut self, factor: f32) {
+        let scale = |speed: u16| {
+            (speed as f32 * factor.max(0.0))
+                .round()
+                .clamp(1.0, MAX_SPEED as f32) as u16
+        };
+        for rain_drop in self.rain_drops.iter_mut() {
+            rain_drop.speed = scale(rain_drop.speed);
+        }
+        let (min_speed, max_speed) = self.options.speed_range;
+        self.options.speed_range = (scale(min_speed), scale(max_speed));
+    }
+
+    /// Cells changed since the last drawn frame packed for sending over
+    /// the wire, see `packed::apply_frame` for the other side
+    pub fn encode_frame(&self) -> Vec<u8> {
+        encode_cells(&self.buffer.diff(&self.render()))
+    }
+
+    /// Change screen size and re-create all drops to fit new screen
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        self.reset();
+    }
+
+    /// Change screen size keeping drops as they are, unlike `resize`
+    /// drops are not re-created and ones outside of new bounds fall off
+    /// naturally. Only drops spawned later use new columns.
+    /// Previous frame is dropped, so next diff covers the whole screen
+    pub fn set_size(&mut self, size: (u16, u16)) {
+        let (width, height) = size;
+        self.fit_columns(width);
+        self.crop_layers(size);
+        self.screen_size = size;
+        self.buffer = Buffer::new(width as usize, height as usize);
+    }
+
+    /// Forget burn-in, splash, reveal and obstacle cells outside of `size`,
+    /// they would be drawn past the screen edges otherwise
+    fn crop_layers(&mut self, size: (u16, u16)) {
+        let (width, height) = size;
+        let inside = |x: u16, y: u16| x < width && y < height;
+        self.burn_in.retain(|(x, y), _| inside(*x, *y));
+        self.splash_cells.retain(|(x, y)| inside(*x, *y));
+        self.reveal_cells.retain(|(x, y, ..)| inside(*x, *y));
+        let old_width = self.obstacles_width as usize;
+        if old_width > 0 {
+            let new_width = old_width.min(width as usize);
+            self.obstacles = self
+                .obstacles
+                .chunks(old_width)
+                .take(height as usize)
+                .flat_map(|row| row.iter().take(new_width).copied())
+                .collect();
+            self.obstacles_width = new_width as u16;
+        }
+    }
+
+    /// Drop spawn columns past new `width` and add ones it opens up,
+    /// new columns are left dead with `dead_column_rate` as on start.
+    /// At least one column is kept alive
+    fn fit_columns(&mut self, width: u16) {
+        let (old_width, _) = self.screen_size;
+        let Self {
+            options,
+            columns,
+            rng,
+            ..
+        } = self;
+        let dead_column_rate = options.dead_column_rate.clamp(0.0, 1.0) as f64;
+        columns.retain(|x| *x < width);
+        columns.extend(
+            (old_width..width)
+                .filter(|x| options.is_lane(*x))
+                .filter(|_| !rng.random_bool(dead_column_rate)),
+        );
+        if self.columns.is_empty() && width > 0 {
+            let x = self.rng.random_range(0..width);
+            self.columns.push(self.options.snap_to_lane(x));
+        }
+    }
+
+    /// Set callback fired with drop id whenever a drop resets during tick
+    pub fn set_on_reset(&mut self, on_reset: impl FnMut(usize) + 'static) {
+        self.on_reset = Some(Box::new(on_reset));
+    }
+
+    /// Remove reset callback
+    pub fn clear_on_reset(&mut self) {
+        self.on_reset = None;
+    }
+
+    /// Remove burn-in marks left by drops
+    pub fn clear_burn_in(&mut self) {
+        self.burn_in.clear();
+    }
+
+    /// Force all cells into single color, `None` brings back style colors
+    pub fn set_monochrome(&mut self, color: Option<gradient::Color>) {
+        self.options.monochrome = color;
+    }
+
+    /// Draw drop with given id emphasized, the drop stays hero after resets.
+    /// Returns `false` if there is no such drop
+    pub fn mark_hero(&mut self, id: usize, style: HeroStyle) -> bool {
+        match self.rain_drops.iter_mut().find(|d| d.id() == id) {
+            Some(rain_drop) => {
+                rain_drop.hero = Some(style);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pause drops in given columns until they are unfrozen
+    pub fn freeze_columns(&mut self, cols: &[u16]) {
+        self.frozen_columns.extend(cols);
+    }
+
+    /// Resume drops in given columns
+    pub fn unfreeze_columns(&mut self, cols: &[u16]) {
+        for col in cols {
+            self.frozen_columns.remove(col);
+        }
+    }
+
+    /// Render drops as Braille dots, each character cell packs 2x4 dots.
+    /// Screen size of the rain is treated as size of the dots canvas,
+    /// so for full terminal it should be twice its width and 4 times height
+    pub fn render_braille(&self) -> Vec<(u16, u16, char)> {
+        let width = self.screen_size.0.div_ceil(2);
+        let height = self.screen_size.1.div_ceil(4);
+        let mut cells = vec![0u8; width as usize * height as usize];
+        for rain_drop in self.rain_drops.iter() {
+            for (x, y, _) in rain_drop.to_points(&self.options) {
+                if x < self.screen_size.0 && y < self.screen_size.1 {
+                    let index =
+                        (y / 4) as usize * width as usize + (x / 2) as usize;
+                    cells[index] |= braille_dot(x % 2, y % 4);
+                }
+            }
+        }
+        cells
+            .into_iter()
+            .enumerate()
+            .filter(|(_, dots)| *dots != 0)
+            .map(|(index, dots)| {
+                let x = (index % width as usize) as u16;
+                let y = (index / width as usize) as u16;
+                (x, y, braille_char(dots))
+            })
+            .collect()
+    }
+
+    /// Top-most glyph drawn at the cell, composited the same way as frames are
+    pub fn glyph_at(&self, x: u16, y: u16) -> Option<char> {
+        if x >= self.screen_size.0
+            || y >= self.screen_size.1
+            || self.is_obstacle(x, y)
+        {
+            return None;
+        }
+        // layers are kept in simulation columns, frame may be mirrored
+        let x = match self.options.mirror_x {
+            true => self.screen_size.0 - 1 - x,
+            false => x,
+        };
+        if let Some((.., glyph, _)) = self
+            .reveal_cells
+            .iter()
+            .find(|(cx, cy, _, locked)| *locked && (*cx, *cy) == (x, y))
+        {
+            return Some(*glyph);
+        }
+        // same draw order and blending as `fill_buffer`
+        let mut rain_drops: Vec<&RainDrop> =
+            self.rain_drops.iter().filter(|d| d.fx == x).collect();
+        rain_drops.sort_by(|a, b| a.speed.partial_cmp(&b.speed).unwrap());
+        let mut top: Option<(f32, char)> = None;
+        for rain_drop in rain_drops.into_iter().rev() {
+            for point in rain_drop.to_points_styled(&self.options) {
+                if (point.x, point.y) != (x, y) {
+                    continue;
+                }
+                match (self.options.same_column_blend, top) {
+                    (SameColumnBlend::Brightest, Some((intensity, _)))
+                        if intensity > point.intensity => {}
+                    _ => top = Some((point.intensity, point.character)),
+                }
+            }
+        }
+        if let Some((_, glyph)) = top {
+            return Some(glyph);
+        }
+        if self.splash_cells.contains(&(x, y)) {
+            return Some('.');
+        }
+        self.burn_in.get(&(x, y)).copied()
+    }
+
+    pub fn fill_buffer(
+        rain_drops: &[RainDrop],
+        buffer: &mut Buffer,
+        gradients: &[Vec<gradient::Color>],
+        options: &DigitalRainOptions,
+    ) {
+        let (width, height) = buffer.get_size();
+        let opacity = options.global_opacity.clamp(0.0, 1.0);
+        // track cells occupied by heads, only needed to blend collisions
+        let mut heads = match options.collision_mode {
+            CollisionMode::ZOrder => vec![],
+            CollisionMode::Additive => vec![false; width * height],
+        };
+        // intensity of cells drawn so far, only needed to keep brightest
+        let mut drawn = match options.same_column_blend {
+            SameColumnBlend::Topmost => vec![],
+            SameColumnBlend::Brightest => vec![f32::NEG_INFINITY; width * height],
+        };
+
+        let mut rain_drops: Vec<&RainDrop> = rain_drops.iter().collect();
+        rain_drops.sort_by(|a, b| a.speed.partial_cmp(&b.speed).unwrap());
+        for rain_drop in rain_drops.into_iter().rev() {
+            for point in rain_drop.to_points_styled(options) {
+                if point.x < width as u16 && point.y < height as u16 {
+                    let (x, y) = (point.x as usize, point.y as usize);
+                    let x = match options.mirror_x {
+                        true => width - 1 - x,
+                        false => x,
+                    };
+                    if !drawn.is_empty() {
+                        let cell_index = buffer.index_of(x, y);
+                        if drawn[cell_index] > point.intensity {
+                            continue;
+                        }
+                        drawn[cell_index] = point.intensity;
+                    }
+                    let mut color = scale_color(
+                        rotate_hue(
+                            pick_color(&rain_drop.style, point.shade, gradients),
+                            point.hue,
+                        ),
+                        point.intensity,
+                    );
+                    if let Some(tint) = rain_drop.palette_color {
+                        color = tint_color(color, tint);
+                    }
+                    if let Some(tint) = options.monochrome {
+                        color = tint_color(color, tint);
+                    }
+                    if let Some(hero) = rain_drop.hero {
+                        color = scale_color(
+                            tint_color(color, hero.color),
+                            hero.brightness,
+                        );
+                    }
+                    if options.new_char_flash
+                        && point.index == 0
+                        && rain_drop.is_head_new()
+                    {
+                        color = FLASH_COLOR;
+                    }
+                    color = scale_color(color, opacity);
+                    if point.index == 0 && !heads.is_empty() {
+                        let cell_index = buffer.index_of(x, y);
+                        if heads[cell_index] {
+                            color = add_colors(buffer.get(x, y).color, color);
+                        }
+                        heads[cell_index] = true;
+                    }
+                    buffer.set(
+                        x,
+                        y,
+                        Cell::new(
+                            point.character,
+                            color,
+                            pick_style(&rain_drop.style, point.shade),
+                        ),
+                    );
+                };
+            }
+        }
+
+        if options.color_support != ColorSupport::TrueColor {
+            for cell in buffer.buffer.iter_mut() {
+                cell.color = downgrade_color(cell.color, options.color_support);
+            }
+        }
+    }
+
+    /// Create new rain drop with unique id placed into one of alive columns
+    fn new_drop(&mut self, rng: &mut impl Rng) -> RainDrop {
+        let drop_id = self.next_drop_id;
+        self.next_drop_id += 1;
+        let mut rain_drop =
+            RainDrop::new(self.screen_size, &self.options, drop_id, rng);
+        let x = self.options.column_bias.pick(&self.columns, rng);
+        rain_drop.set_column(x);
+        if self.options.per_column_speed {
+            rain_drop.speed = self.options.column_speed(x);
+        }
+        if let Some(palette) = &self.options.palette_cycle
+            && !palette.is_empty()
+        {
+            rain_drop.palette_color = Some(palette[(drop_id - 1) % palette.len()]);
+        }
+        if let Some((_, pool)) = self.rotated_pool.as_ref() {
+            rain_drop.pick_glyphs_from(&self.options, pool, rng);
+        }
+        rain_drop
+    }
+
+    /// Add one more worm with decent chance
+    pub fn add_one(&mut self) {
+        self.using_own_rng(|rain, rng| rain.spawn_one(rng));
+    }
+
+    /// Add one more drop with decent chance using given rng
+    fn spawn_one(&mut self, rng: &mut impl Rng) {
+        let max_drops = self
+            .options
+            .get_max_drops_number()
+            .min(self.options.get_drops_cap(self.screen_size.0));
+        // burst drops are not a part of steady population
+        let steady = self.rain_drops.iter().filter(|d| !d.burst).count();
+        if steady >= max_drops as usize {
+            return;
+        };
+        if rng.random_range(0.0..=1.0) <= 0.3 {
+            let rain_drop = self.new_drop(rng);
+            self.rain_drops.push(rain_drop);
+        };
+    }
+
+    /// Number of rain drops currently alive
+    pub fn drop_count(&self) -> usize {
+        self.rain_drops.len()
+    }
+
+    /// Spawn one random drop regardless of drops range, returns its id
+    pub fn add_drop(&mut self) -> usize {
+        let rain_drop = self.using_own_rng(|rain, rng| rain.new_drop(rng));
+        let drop_id = rain_drop.id();
+        self.rain_drops.push(rain_drop);
+        drop_id
+    }
+
+    /// Spawn a wave of `count` fast short drops at once on top of steady
+    /// population, they are removed once they pass the screen
+    pub fn burst(&mut self, count: usize) {
+        self.using_own_rng(|rain, rng| {
+            for _ in 0..count {
+                let mut rain_drop = rain.new_drop(rng);
+                rain_drop.speed = rain.options.get_max_speed();
+                rain_drop.max_length = rng.random_range(BURST_LENGTH);
+                rain_drop.body.truncate(rain_drop.max_length);
+                rain_drop.burst = true;
+                rain.rain_drops.push(rain_drop);
+            }
+        });
+    }
+
+    /// Remove drop by id, returns `false` if there is no such drop
+    pub fn remove_drop(&mut self, id: usize) -> bool {
+        match self.rain_drops.iter().position(|d| d.id() == id) {
+            Some(index) => {
+                self.rain_drops.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Weights of character groups at `phase` of pool rotation, each group
+/// peaks in turn while the rest fade down to weight 1
+fn rotation_weights(phase: f32) -> HashMap<CharGroup, u32> {
+    let count = CharGroup::ALL.len() as f32;
+    CharGroup::ALL
+        .into_iter()
+        .enumerate()
+        .map(|(index, group)| {
+            let peak = index as f32 / count;
+            let closeness = (1.0 + (TAU * (phase - peak)).cos()) / 2.0;
+            let extra = (closeness * ROTATION_PEAK_WEIGHT as f32).round() as u32;
+            (group, 1 + extra)
+        })
+        .collect()
+}
+
+impl Default for DigitalRainOptions {
+    fn default() -> Self {
+        DigitalRainOptionsBuilder::default().build().unwrap()
+    }
+}
+
+impl DigitalRainOptions {
+    /// Check options are consistent
+    pub fn validate(&self) -> Result<(), DigitalRainOptionsError> {
+        let (min_drops, max_drops) = self.drops_range;
+        if min_drops > max_drops {
+            return Err(DigitalRainOptionsError::InvalidDropsRange(
+                min_drops, max_drops,
+            ));
+        }
+        let (min_speed, max_speed) = self.speed_range;
+        if min_speed > max_speed {
+            return Err(DigitalRainOptionsError::InvalidSpeedRange(
+                min_speed, max_speed,
+            ));
+        }
+        if max_speed == 0 {
+            return Err(DigitalRainOptionsError::ZeroSpeed);
+        }
+        if self.aspect_ratio.is_nan() || self.aspect_ratio <= 0.0 {
+            return Err(DigitalRainOptionsError::InvalidAspectRatio(
+                self.aspect_ratio,
+            ));
+        }
+        let rates = [
+            ("dead_column_rate", self.dead_column_rate),
+            ("burn_in_rate", self.burn_in_rate),
+            ("ephemeral_rate", self.ephemeral_rate),
+            ("body_fill", self.body_fill),
+            ("global_opacity", self.global_opacity),
+        ];
+        let rare_head = self
+            .rare_head
+            .as_ref()
+            .map(|(rate, _)| ("rare_head", *rate));
+        for (name, rate) in rates.into_iter().chain(rare_head) {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(DigitalRainOptionsError::RateOutOfRange(name, rate));
+            }
+        }
+        if let Some((min, max)) = self.length_range
+            && (min == 0 || min > max)
+        {
+            return Err(DigitalRainOptionsError::InvalidLengthRange(min, max));
+        }
+        // characters drawn next to clusters must not look like their codes
+        let reserved = GRAPHEME_BASE..GRAPHEME_BASE + self.graphemes.len() as u32;
+        let rare_head = self.rare_head.iter().flat_map(|(_, glyphs)| glyphs);
+        if let Some(character) = self
+            .characters
+            .iter()
+            .chain(self.per_drop_charsets.iter().flatten())
+            .chain(self.style_charsets.values().flatten())
+            .chain(self.region_charsets.iter().flat_map(|(_, set)| set))
+            .chain(self.tail_light_chars.iter().flatten())
+            .chain(rare_head)
+            .find(|c| reserved.contains(&(**c as u32)))
+        {
+            return Err(DigitalRainOptionsError::ReservedCharacter(*character));
+        }
+        let durations = [
+            ("start_delay_range", Some(self.start_delay_range)),
+            ("flicker_interval_range", self.flicker_interval_range),
+        ];
+        for (name, range) in durations {
+            if let Some((min, max)) = range
+                && min > max
+            {
+                return Err(DigitalRainOptionsError::InvalidDurationRange(
+                    name, min, max,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Overwrite options set in `patch`, the rest stay intact.
+    /// Handy to layer user tweaks on top of a base theme
+    pub fn apply(&mut self, patch: OptionsPatch) {
+        fn overwrite<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+
+        let OptionsPatch {
+            drops_range,
+            speed_range,
+            collision_mode,
+            aspect_ratio,
+            min_columns_per_drop,
+            dead_column_rate,
+            splash,
+            init_body_mode,
+            tail_direction,
+            per_drop_charsets,
+            max_step,
+            characters,
+            monochrome,
+            antialias_head,
+            mirror_x,
+            char_spacing,
+            instant_respawn,
+            rare_head,
+            wiggle,
+            allowed_styles,
+            color_support,
+            max_inserts_per_tick,
+            style_charsets,
+            ghost_head,
+            cell_rounding,
+            per_column_speed,
+            body_fill,
+            horizontal_edge,
+            reveal,
+            tail_floor,
+            tail_floor_jitter,
+            column_bias,
+            hue_shift,
+            char_source,
+            preroll_rows,
+            burn_in_rate,
+            tail_light_chars,
+            start_delay_range,
+            graphemes,
+            char_groups,
+            narrow_only,
+            flicker_interval_range,
+            stagger,
+            glyphs_per_drop,
+            global_opacity,
+            ephemeral_rate,
+            length_range,
+            speed_brightness,
+            region_charsets,
+            heads_only,
+            palette_cycle,
+            ease_out_zone,
+            top_fade,
+            lane_spacing,
+            prefill,
+            same_column_blend,
+            spawn_fade,
+            new_char_flash,
+            group_weights,
+            pool_rotation,
+        } = patch;
+        overwrite(&mut self.drops_range, drops_range);
+        overwrite(&mut self.speed_range, speed_range);
+        overwrite(&mut self.collision_mode, collision_mode);
+        overwrite(&mut self.aspect_ratio, aspect_ratio);
+        overwrite(&mut self.min_columns_per_drop, min_columns_per_drop);
+        overwrite(&mut self.dead_column_rate, dead_column_rate);
+        overwrite(&mut self.splash, splash);
+        overwrite(&mut self.init_body_mode, init_body_mode);
+        overwrite(&mut self.tail_direction, tail_direction);
+        overwrite(&mut self.per_drop_charsets, per_drop_charsets);
+        overwrite(&mut self.max_step, max_step);
+        overwrite(&mut self.characters, characters);
+        overwrite(&mut self.monochrome, monochrome);
+        overwrite(&mut self.antialias_head, antialias_head);
+        overwrite(&mut self.mirror_x, mirror_x);
+        overwrite(&mut self.char_spacing, char_spacing);
+        overwrite(&mut self.instant_respawn, instant_respawn);
+        overwrite(&mut self.rare_head, rare_head);
+        overwrite(&mut self.wiggle, wiggle);
+        overwrite(&mut self.allowed_styles, allowed_styles);
+        overwrite(&mut self.color_support, color_support);
+        overwrite(&mut self.max_inserts_per_tick, max_inserts_per_tick);
+        overwrite(&mut self.style_charsets, style_charsets);
+        overwrite(&mut self.ghost_head, ghost_head);
+        overwrite(&mut self.cell_rounding, cell_rounding);
+        overwrite(&mut self.per_column_speed, per_column_speed);
+        overwrite(&mut self.body_fill, body_fill);
+        overwrite(&mut self.horizontal_edge, horizontal_edge);
+        overwrite(&mut self.reveal, reveal);
+        overwrite(&mut self.tail_floor, tail_floor);
+        overwrite(&mut self.tail_floor_jitter, tail_floor_jitter);
+        overwrite(&mut self.column_bias, column_bias);
+        overwrite(&mut self.hue_shift, hue_shift);
+        overwrite(&mut self.char_source, char_source);
+        overwrite(&mut self.preroll_rows, preroll_rows);
+        overwrite(&mut self.burn_in_rate, burn_in_rate);
+        overwrite(&mut self.tail_light_chars, tail_light_chars);
+        overwrite(&mut self.start_delay_range, start_delay_range);
+        overwrite(&mut self.graphemes, graphemes);
+        overwrite(&mut self.char_groups, char_groups);
+        overwrite(&mut self.narrow_only, narrow_only);
+        overwrite(&mut self.flicker_interval_range, flicker_interval_range);
+        overwrite(&mut self.stagger, stagger);
+        overwrite(&mut self.glyphs_per_drop, glyphs_per_drop);
+        overwrite(&mut self.global_opacity, global_opacity);
+        overwrite(&mut self.ephemeral_rate, ephemeral_rate);
+        overwrite(&mut self.length_range, length_range);
+        overwrite(&mut self.speed_brightness, speed_brightness);
+        overwrite(&mut self.region_charsets, region_charsets);
+        overwrite(&mut self.heads_only, heads_only);
+        overwrite(&mut self.palette_cycle, palette_cycle);
+        overwrite(&mut self.ease_out_zone, ease_out_zone);
+        overwrite(&mut self.top_fade, top_fade);
+        overwrite(&mut self.lane_spacing, lane_spacing);
+        overwrite(&mut self.prefill, prefill);
+        overwrite(&mut self.same_column_blend, same_column_blend);
+        overwrite(&mut self.spawn_fade, spawn_fade);
+        overwrite(&mut self.new_char_flash, new_char_flash);
+        overwrite(&mut self.group_weights, group_weights);
+        overwrite(&mut self.pool_rotation, pool_rotation);
+    }
+
+    /// Same as `apply` but consumes and returns options
+    pub fn with_overrides(mut self, patch: OptionsPatch) -> Self {
+        self.apply(patch);
+        self
+    }
+
+    /// Characters a drop with given per drop charset and style is made of:
+    /// per drop set, then style set, then `characters`, then default groups,
+    /// wide glyphs are left out with `narrow_only` unless none are left
+    pub fn character_pool(
+        &self,
+        charset: Option<usize>,
+        style: RainDropStyle,
+    ) -> Cow<'_, [char]> {
+        self.pool_or(charset, style, || self.default_pool())
+    }
+
+    /// Same as `character_pool` with `default_pool` in place of default
+    /// characters
+    pub fn character_pool_or<'a>(
+        &'a self,
+        charset: Option<usize>,
+        style: RainDropStyle,
+        default_pool: &'a [char],
+    ) -> Cow<'a, [char]> {
+        self.pool_or(charset, style, || Cow::Borrowed(default_pool))
+    }
+
+    fn pool_or<'a>(
+        &'a self,
+        charset: Option<usize>,
+        style: RainDropStyle,
+        default_pool: impl FnOnce() -> Cow<'a, [char]>,
+    ) -> Cow<'a, [char]> {
+        let charset = charset.and_then(|index| self.per_drop_charsets.get(index));
+        let style_charset = self.style_charsets.get(&style);
+        let mut pool = match (charset, style_charset) {
+            (Some(charset), _) if !charset.is_empty() => {
+                Cow::Borrowed(&charset[..])
+            }
+            (_, Some(charset)) if !charset.is_empty() => {
+                Cow::Borrowed(&charset[..])
+            }
+            _ if !self.characters.is_empty() => Cow::Borrowed(&self.characters[..]),
+            _ => default_pool(),
+        };
+        if self.narrow_only && pool.iter().any(|c| is_wide(*c)) {
+            let narrow: Vec<char> =
+                pool.iter().copied().filter(|c| !is_wide(*c)).collect();
+            if !narrow.is_empty() {
+                pool = Cow::Owned(narrow);
+            }
+        }
+        pool
+    }
+
+    /// Whether drops may spawn in column `x` given `lane_spacing`
+    pub fn is_lane(&self, x: u16) -> bool {
+        match self.lane_spacing {
+            Some(spacing) if spacing > 1 => x.is_multiple_of(spacing),
+            _ => true,
+        }
+    }
+
+    /// Closest lane at or left of column `x`
+    pub fn snap_to_lane(&self, x: u16) -> u16 {
+        match self.lane_spacing {
+            Some(spacing) if spacing > 1 => x - x % spacing,
+            _ => x,
+        }
+    }
+
+    /// Characters of the first screen region containing `row`,
+    /// rows above the screen belong to the top one
+    pub fn region_charset(&self, row: i32) -> Option<&[char]> {
+        let row = row.clamp(0, u16::MAX as i32) as u16;
+        self.region_charsets
+            .iter()
+            .find(|(rows, characters)| {
+                rows.contains(&row) && !characters.is_empty()
+            })
+            .map(|(_, characters)| characters.as_slice())
+    }
+
+    /// Default characters restricted to `char_groups`, characters are
+    /// repeated by `group_weights`
+    fn default_pool(&self) -> Cow<'_, [char]> {
+        self.weighted_pool(&self.group_weights)
+    }
+
+    /// Default characters restricted to `char_groups`, characters are
+    /// repeated by `weights`
+    fn weighted_pool(&self, weights: &HashMap<CharGroup, u32>) -> Cow<'_, [char]> {
+        if self.char_groups.is_empty() && weights.is_empty() {
+            return Cow::Borrowed(default_characters());
+        }
+        let pool: Vec<char> = CharGroup::ALL
+            .into_iter()
+            .filter(|group| {
+                self.char_groups.is_empty() || self.char_groups.contains(group)
+            })
+            .flat_map(|group| {
+                let weight = weights.get(&group).copied().unwrap_or(1);
+                group.chars().repeat(weight as usize)
+            })
+            .collect();
+        match pool.is_empty() {
+            true => Cow::Borrowed(default_characters()),
+            false => Cow::Owned(pool),
+        }
+    }
+
+    /// All characters drops may be made of after every filter,
+    /// without duplicates, handy for debugging and pickers
+    pub fn effective_characters(&self) -> Vec<char> {
+        let charsets: Vec<Option<usize>> = match self.per_drop_charsets.len() {
+            0 => vec![None],
+            len => (0..len).map(Some).collect(),
+        };
+        let styles = match self.allowed_styles.is_empty() {
+            true => RainDropStyle::ALL.to_vec(),
+            false => self.allowed_styles.clone(),
+        };
+        let mut pools = vec![];
+        for charset in charsets {
+            for style in styles.iter() {
+                pools.push(self.character_pool(charset, *style));
+            }
+        }
+        for (_, characters) in self.region_charsets.iter() {
+            pools.push(Cow::Borrowed(characters.as_slice()));
+        }
+
+        let mut seen = HashSet::new();
+        pools
+            .iter()
+            .flat_map(|pool| pool.iter().copied())
+            .filter(|c| seen.insert(*c))
+            .collect()
+    }
+
+    /// Text drawn for body character, grapheme cluster for codes
+    /// standing for `graphemes` and the character itself otherwise
+    pub fn glyph(&self, symbol: char) -> Cow<'_, str> {
+        match self.cluster(symbol) {
+            Some(cluster) => Cow::Borrowed(cluster),
+            None => Cow::Owned(symbol.to_string()),
+        }
+    }
+
+    /// Grapheme cluster `symbol` stands for, `None` for plain characters.
+    /// Custom character source wins over graphemes, so its characters
+    /// are never taken for clusters
+    pub fn cluster(&self, symbol: char) -> Option<&str> {
+        if self.char_source.is_some() {
+            return None;
+        }
+        (symbol as u32)
+            .checked_sub(GRAPHEME_BASE)
+            .and_then(|index| self.graphemes.get(index as usize))
+            .map(String::as_str)
+    }
+
+    #[inline]
+    pub fn get_min_drops_number(&self) -> u16 {
+        self.drops_range.0
+    }
+
+    #[inline]
+    pub fn get_max_drops_number(&self) -> u16 {
+        self.drops_range.1
+    }
+
+    /// Maximum drops number which fits screen width given columns per drop
+    #[inline]
+    pub fn get_drops_cap(&self, width: u16) -> u16 {
+        match self.min_columns_per_drop {
+            0 => u16::MAX,
+            columns => (width / columns).max(1),
+        }
+    }
+
+    /// Speed from the speed range fixed for the column
+    pub fn column_speed(&self, x: u16) -> u16 {
+        let (min_speed, max_speed) = (self.get_min_speed(), self.get_max_speed());
+        // cheap integer hash, so neighbour columns get unrelated speeds
+        let hash = (x as u32).wrapping_mul(2_654_435_761) >> 16;
+        let span = max_speed.saturating_sub(min_speed) as u32 + 1;
+        min_speed + (hash % span) as u16
+    }
+
+    #[inline]
+    pub fn get_min_speed(&self) -> u16 {
+        self.speed_range.0
+    }
+
+    /// Speed range moving drops by `rows` per frame when ticked `fps` times
+    /// per second, speed is rounded to whole cells per second
+    pub fn speed_for_rows_per_frame(rows: f32, fps: f32) -> (u16, u16) {
+        let speed = (rows * fps).round().clamp(1.0, MAX_SPEED as f32) as u16;
+        (speed, speed)
+    }
+
+    #[inline]
+    pub fn get_max_speed(&self) -> u16 {
+        self.speed_range.1
+    }
+}
+
+impl DefaultOptions for DigitalRain {
+    type Options = DigitalRainOptions;
+
+    fn default_options(width: u16, height: u16) -> Self::Options {
+        let drops_range = {
+            let min_drops = (width * height) / 160; // Approximately 0.6% of screen space
+            let max_drops = (width * height) / 80; // Approximately 1.2% of screen space
+            (min_drops.max(10), max_drops.max(20)) // Ensure minimum values
+        };
+
+        let speed_range = {
+            let min_speed = (height / 20).max(2); // Faster for larger screens
+            let max_speed = (height / 10).max(16); // But not too fast
+            (min_speed, max_speed)
+        };
+
+        DigitalRainOptionsBuilder::default()
+            .drops_range(drops_range)
+            .speed_range(speed_range)
+            .build()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_sane_default_options() -> DigitalRainOptions {
+        DigitalRainOptionsBuilder::default()
+            .drops_range((20, 30))
+            .speed_range((10, 20))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn default_options_match_builder() {
+        let options = DigitalRainOptions::default();
+        assert_eq!(
+            options,
+            DigitalRainOptionsBuilder::default().build().unwrap()
+        );
+        assert_eq!(options.drops_range, DEFAULT_DROPS_RANGE);
+        assert_eq!(options.speed_range, DEFAULT_SPEED_RANGE);
+        assert_eq!(options.validate(), Ok(()));
+
+        let options = DigitalRainOptions {
+            speed_range: (10, 2),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(DigitalRainOptionsError::InvalidSpeedRange(10, 2))
+        );
+    }
+
+    #[test]
+    fn zero_speed_rejected() {
+        let error = DigitalRainOptionsBuilder::default()
+            .speed_range((0, 0))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            DigitalRainOptionsError::ZeroSpeed.to_string()
+        );
+
+        let options = DigitalRainOptions {
+            speed_range: (0, 0),
+            ..Default::default()
+        };
+        assert_eq!(options.validate(), Err(DigitalRainOptionsError::ZeroSpeed));
+    }
+
+    #[test]
+    fn out_of_range_options_rejected() {
+        let options = DigitalRainOptions {
+            global_opacity: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(DigitalRainOptionsError::RateOutOfRange(
+                "global_opacity",
+                1.5
+            ))
+        );
+
+        let options = DigitalRainOptions {
+            rare_head: Some((-0.1, vec!['*'])),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(DigitalRainOptionsError::RateOutOfRange("rare_head", -0.1))
+        );
+
+        let options = DigitalRainOptions {
+            length_range: Some((0, 5)),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(DigitalRainOptionsError::InvalidLengthRange(0, 5))
+        );
+
+        let long = Duration::from_secs(2);
+        let options = DigitalRainOptions {
+            flicker_interval_range: Some((long, Duration::ZERO)),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.validate(),
+            Err(DigitalRainOptionsError::InvalidDurationRange(
+                "flicker_interval_range",
+                long,
+                Duration::ZERO
+            ))
+        );
+    }
+
+    #[test]
+    fn ranges_required_when_deserializing() {
+        let error = toml::from_str::<DigitalRainOptions>("drops_range = [1, 2]")
+            .unwrap_err();
+        assert!(error.to_string().contains("speed_range"));
+
+        let options: DigitalRainOptions =
+            toml::from_str("drops_range = [1, 2]\nspeed_range = [3, 4]").unwrap();
+        assert_eq!(options.drops_range, (1, 2));
+        assert_eq!(options.speed_range, (3, 4));
+        assert_eq!(options.aspect_ratio, 2.0);
+        assert!(options.prefill);
+    }
+
+    #[test]
+    fn non_positive_aspect_ratio_rejected() {
+        for aspect_ratio in [0.0, -1.0, f32::NAN] {
+            let options = DigitalRainOptions {
+                aspect_ratio,
+                ..Default::default()
+            };
+            assert!(matches!(
+                options.validate(),
+                Err(DigitalRainOptionsError::InvalidAspectRatio(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn speed_cells_per_sec_setters() {
+        let by_range = DigitalRainOptionsBuilder::default()
+            .drops_range((10, 20))
+            .speed_range((5, 30))
+            .build()
+            .unwrap();
+        let by_bounds = DigitalRainOptionsBuilder::default()
+            .drops_range((10, 20))
+            .min_speed_cells_per_sec(5)
+            .max_speed_cells_per_sec(30)
+            .build()
+            .unwrap();
+        assert_eq!(by_range, by_bounds);
+
+        let only_max = DigitalRainOptionsBuilder::default()
+            .drops_range((10, 20))
+            .max_speed_cells_per_sec(30)
+            .build()
+            .unwrap();
+        assert_eq!(only_max.speed_range, (DEFAULT_SPEED_RANGE.0, 30));
+    }
+
+    #[test]
+    fn effective_digits_only() {
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((10, 20))
+            .speed_range(DEFAULT_SPEED_RANGE)
+            .char_groups(vec![CharGroup::Digits])
+            .narrow_only(true)
+            .build()
+            .unwrap();
+        let expected: Vec<char> = ('0'..='9').collect();
+        assert_eq!(options.effective_characters(), expected);
+
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((10, 20))
+            .speed_range(DEFAULT_SPEED_RANGE)
+            .characters(vec!['a', '\u{65e5}', 'b', 'a'])
+            .narrow_only(true)
+            .build()
+            .unwrap();
+        assert_eq!(options.effective_characters(), vec!['a', 'b']);
+    }
+
+    #[test]
+    fn patch_overwrites_set_fields_only() {
+        let mut options = DigitalRainOptionsBuilder::default()
+            .speed_range(DEFAULT_SPEED_RANGE)
+            .drops_range((5, 7))
+            .theme(Theme::Binary)
+            .build()
+            .unwrap();
+        let base = options.clone();
+        options.apply(OptionsPatch::default().speed_range((3, 9)).clone());
+        assert_eq!(options.speed_range, (3, 9));
+        assert_eq!(
+            base.clone().with_overrides(
+                OptionsPatch::default().speed_range((3, 9)).clone()
+            ),
+            options
+        );
+        assert_eq!(
+            options,
+            DigitalRainOptions {
+                speed_range: (3, 9),
+                ..base
+            }
+        );
+    }
+
+    #[test]
+    fn create_new() {
+        let foo = DigitalRain::new(get_sane_default_options(), (100, 100));
+        assert_eq!(foo.rain_drops.len(), 20);
+    }
+
+    #[test]
+    fn no_diff() {
+        let mut foo = DigitalRain::new(get_sane_default_options(), (100, 100));
+        let q = foo.get_diff();
         assert!(q.is_empty());
     }
 
+    #[test]
+    fn additive_collision_is_brighter() {
+        use super::super::draw::to_rgb;
+        let rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        let head_brightness = |collision_mode: CollisionMode| {
+            let drops = vec![
+                RainDrop::from_values(
+                    1,
+                    vec!['a'],
+                    RainDropStyle::Back,
+                    5,
+                    5.0,
+                    10,
+                    4,
+                ),
+                RainDrop::from_values(
+                    2,
+                    vec!['b'],
+                    RainDropStyle::Back,
+                    5,
+                    5.0,
+                    10,
+                    6,
+                ),
+            ];
+            let mut options = get_sane_default_options();
+            options.collision_mode = collision_mode;
+            let mut buffer = Buffer::new(30, 30);
+            DigitalRain::fill_buffer(
+                &drops,
+                &mut buffer,
+                &rain.gradients,
+                &options,
+            );
+            let (r, g, b) = to_rgb(buffer.get(5, 5).color);
+            r as u16 + g as u16 + b as u16
+        };
+
+        assert!(
+            head_brightness(CollisionMode::Additive)
+                > head_brightness(CollisionMode::ZOrder)
+        );
+    }
+
+    #[test]
+    fn same_column_brightest_keeps_brighter_cells() {
+        let rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        // slow long drop is on top, its tail is dim where bodies overlap
+        let drops = vec![
+            RainDrop::from_values(
+                1,
+                vec!['a'; 10],
+                RainDropStyle::Front,
+                5,
+                15.0,
+                10,
+                4,
+            ),
+            RainDrop::from_values(
+                2,
+                vec!['b'; 3],
+                RainDropStyle::Solid,
+                5,
+                11.0,
+                10,
+                6,
+            ),
+        ];
+        let glyphs = |blend: SameColumnBlend| {
+            let mut options = get_sane_default_options();
+            options.same_column_blend = blend;
+            options.tail_floor = 0.2;
+            let mut buffer = Buffer::new(30, 30);
+            DigitalRain::fill_buffer(
+                &drops,
+                &mut buffer,
+                &rain.gradients,
+                &options,
+            );
+            (9..=11)
+                .map(|y| buffer.get(5, y).symbol)
+                .collect::<String>()
+        };
+        assert_eq!(glyphs(SameColumnBlend::Topmost), "aaa");
+        assert_eq!(glyphs(SameColumnBlend::Brightest), "bbb");
+    }
+
+    #[test]
+    fn glyph_at_matches_render() {
+        let mut options = get_sane_default_options();
+        options.splash = true;
+        options.burn_in_rate = 0.5;
+        options.ghost_head = true;
+        let mut rain = DigitalRain::new_seeded(options, (30, 20), 4);
+        for _ in 0..40 {
+            rain.tick(Duration::from_millis(50));
+            let buffer = rain.render();
+            for x in 0..30 {
+                for y in 0..20 {
+                    let cell = buffer.get(x as usize, y as usize);
+                    let expected = (cell != Cell::default()).then_some(cell.symbol);
+                    assert_eq!(rain.glyph_at(x, y), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn glyph_at_head_position() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Front,
+            5,
+            10.0,
+            10,
+            4,
+        )];
+        assert_eq!(rain.glyph_at(5, 10), Some('a'));
+        assert_eq!(rain.glyph_at(5, 8), Some('c'));
+        assert_eq!(rain.glyph_at(5, 11), None);
+        assert_eq!(rain.glyph_at(6, 10), None);
+        assert_eq!(rain.glyph_at(100, 100), None);
+    }
+
+    #[test]
+    fn drops_capped_on_narrow_screen() {
+        let mut options = get_sane_default_options();
+        options.min_columns_per_drop = 2;
+        let mut rain = DigitalRain::new(options, (5, 30));
+        assert_eq!(rain.rain_drops.len(), 2);
+        for _ in 0..100 {
+            rain.update();
+            assert!(rain.rain_drops.len() <= 2);
+        }
+
+        rain.resize(10, 30);
+        assert_eq!(rain.rain_drops.len(), 5);
+    }
+
+    #[test]
+    fn dead_columns_never_get_drops() {
+        let mut options = get_sane_default_options();
+        options.dead_column_rate = 0.5;
+        let mut rain = DigitalRain::new(options, (100, 10));
+        assert!(rain.columns.len() > 25 && rain.columns.len() < 75);
+
+        let mut visited = std::collections::HashSet::new();
+        for _ in 0..1000 {
+            rain.update();
+            visited.extend(rain.rain_drops.iter().map(|d| d.fx));
+        }
+        assert!(visited.iter().all(|x| rain.columns.contains(x)));
+        assert!(visited.len() < 75);
+
+        // columns opened up by widening are rolled the same way
+        rain.resize(300, 10);
+        let opened = rain.columns.iter().filter(|x| **x >= 100).count();
+        assert!(opened > 50 && opened < 150);
+    }
+
+    #[test]
+    fn frozen_column_does_not_advance() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        rain.rain_drops = vec![
+            RainDrop::from_values(
+                1,
+                vec!['a'],
+                RainDropStyle::Front,
+                3,
+                5.0,
+                10,
+                10,
+            ),
+            RainDrop::from_values(
+                2,
+                vec!['b'],
+                RainDropStyle::Front,
+                5,
+                5.0,
+                10,
+                10,
+            ),
+        ];
+        rain.freeze_columns(&[3]);
+        rain.update();
+        assert_eq!(rain.rain_drops[0].fy, 5.0);
+        assert!(rain.rain_drops[1].fy > 5.0);
+
+        rain.unfreeze_columns(&[3]);
+        rain.update();
+        assert!(rain.rain_drops[0].fy > 5.0);
+    }
+
+    #[test]
+    fn splash_on_bottom_row() {
+        let mut options = get_sane_default_options();
+        options.splash = true;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            5,
+            28.0,
+            10,
+            10,
+        )];
+        rain.tick(Duration::from_millis(50));
+        assert_eq!(rain.rain_drops[0].to_point(), (5, 29));
+        for x in [3, 4, 6, 7] {
+            assert!(rain.splash_cells.contains(&(x, 29)));
+            assert_eq!(rain.glyph_at(x, 29), Some('.'));
+        }
+
+        rain.tick(Duration::from_millis(50));
+        assert!(rain.splash_cells.is_empty());
+    }
+
+    #[test]
+    fn splash_reach_follows_aspect_ratio() {
+        let mut options = get_sane_default_options();
+        options.splash = true;
+        options.aspect_ratio = 1.0;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            5,
+            28.0,
+            10,
+            10,
+        )];
+        rain.tick(Duration::from_millis(50));
+        let mut cells = rain.splash_cells.clone();
+        cells.sort_unstable();
+        assert_eq!(cells, vec![(4, 29), (6, 29)]);
+    }
+
+    #[test]
+    fn add_and_remove_drop_by_id() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        assert_eq!(rain.drop_count(), 20);
+
+        let first = rain.add_drop();
+        let second = rain.add_drop();
+        assert_ne!(first, second);
+        assert_eq!(rain.drop_count(), 22);
+
+        assert!(rain.remove_drop(first));
+        assert!(!rain.remove_drop(first));
+        assert_eq!(rain.drop_count(), 21);
+        assert!(rain.rain_drops.iter().any(|d| d.id() == second));
+    }
+
+    #[test]
+    fn braille_single_dot() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (60, 120));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            3,
+            5.0,
+            10,
+            10,
+        )];
+        assert_eq!(rain.render_braille(), vec![(1, 1, '\u{2810}')]);
+
+        // whole left column of the cell
+        rain.rain_drops[0].body = vec!['a', 'b', 'c', 'd'];
+        rain.rain_drops[0].fx = 2;
+        rain.rain_drops[0].fy = 7.0;
+        assert_eq!(rain.render_braille(), vec![(1, 1, '\u{2847}')]);
+
+        // gaps of char spacing are left out as in frames
+        rain.options.char_spacing = 2;
+        assert_eq!(rain.render_braille(), vec![(1, 1, '\u{2842}')]);
+    }
+
+    #[test]
+    fn on_reset_callback_counts_resets() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((1, 1))
+            .speed_range((10, 20))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            5,
+            40.0,
+            10,
+            10,
+        )];
+        let resets = Rc::new(RefCell::new(vec![]));
+        let observed = resets.clone();
+        rain.set_on_reset(move |id| observed.borrow_mut().push(id));
+
+        rain.tick(Duration::from_millis(50));
+        assert_eq!(*resets.borrow(), vec![1]);
+
+        // 30 rows screen is passed by 20 cells/sec drop in few seconds
+        for _ in 0..400 {
+            rain.tick(Duration::from_millis(50));
+        }
+        assert!(resets.borrow().len() > 3);
+        assert!(resets.borrow().iter().all(|id| *id == 1));
+
+        rain.clear_on_reset();
+        let count = resets.borrow().len();
+        for _ in 0..400 {
+            rain.tick(Duration::from_millis(50));
+        }
+        assert_eq!(resets.borrow().len(), count);
+    }
+
+    #[test]
+    fn binary_theme() {
+        let options = DigitalRainOptionsBuilder::default()
+            .speed_range(DEFAULT_SPEED_RANGE)
+            .drops_range((20, 30))
+            .theme(Theme::Binary)
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (30, 30));
+        for _ in 0..100 {
+            rain.update();
+        }
+        for rain_drop in rain.rain_drops.iter() {
+            assert!(rain_drop.body.iter().all(|c| *c == '0' || *c == '1'));
+        }
+        for (_, _, cell) in rain.get_diff() {
+            if let style::Color::Rgb { r, g: _, b } = cell.color {
+                assert_eq!((r, b), (0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn mirror_x_flips_columns() {
+        let mut options = get_sane_default_options();
+        options.mirror_x = true;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            0,
+            10.0,
+            10,
+            10,
+        )];
+        assert_eq!(rain.glyph_at(29, 10), Some('a'));
+        assert_eq!(rain.glyph_at(0, 10), None);
+    }
+
+    #[test]
+    fn set_monochrome_toggles_head_hue() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        rain.rain_drops = RainDropStyle::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, style)| {
+                RainDrop::from_values(
+                    i,
+                    vec!['a'; 3],
+                    *style,
+                    i as u16,
+                    10.0,
+                    10,
+                    10,
+                )
+            })
+            .collect();
+        let head_colors = |rain: &DigitalRain| -> Vec<style::Color> {
+            let buffer = rain.render();
+            (0..RainDropStyle::ALL.len())
+                .map(|x| buffer.get(x, 10).color)
+                .collect()
+        };
+
+        rain.set_monochrome(Some(gradient::Color { r: 0, g: 255, b: 0 }));
+        for color in head_colors(&rain) {
+            let style::Color::Rgb { r, g, b } = color else {
+                panic!("unexpected color {color:?}");
+            };
+            assert_eq!((r, b), (0, 0));
+            assert!(g > 0);
+        }
+
+        rain.set_monochrome(None);
+        assert!(head_colors(&rain).iter().any(|color| matches!(
+            color,
+            style::Color::Rgb { r, .. } if *r > 0
+        )));
+    }
+
+    #[test]
+    fn visible_cell_count_matches_frame() {
+        for (antialias_head, char_spacing, ghost_head, body_fill) in [
+            (false, 1, false, 1.0),
+            (true, 1, false, 1.0),
+            (false, 3, false, 1.0),
+            (true, 2, true, 1.0),
+            (false, 1, true, 1.0),
+            (false, 1, true, 0.6),
+            (true, 2, false, 0.6),
+        ] {
+            let mut options = get_sane_default_options();
+            options.antialias_head = antialias_head;
+            options.char_spacing = char_spacing;
+            options.ghost_head = ghost_head;
+            options.body_fill = body_fill;
+            let mut rain = DigitalRain::new(options, (40, 30));
+            for _ in 0..200 {
+                rain.update();
+                assert_eq!(rain.visible_cell_count(), rain.frame().count());
+            }
+        }
+    }
+
+    #[test]
+    fn color_support_detection() {
+        assert_eq!(
+            color_support_from_env(Some("truecolor"), Some("xterm-256color")),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            color_support_from_env(None, Some("xterm-256color")),
+            ColorSupport::Ansi256
+        );
+        assert_eq!(
+            color_support_from_env(None, Some("linux")),
+            ColorSupport::Ansi16
+        );
+        assert_eq!(color_support_from_env(None, None), ColorSupport::Ansi16);
+    }
+
+    #[test]
+    fn downgraded_render_has_no_rgb() {
+        let mut options = get_sane_default_options();
+        options.color_support = ColorSupport::Ansi256;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        for _ in 0..20 {
+            rain.update();
+        }
+        assert!(
+            rain.render()
+                .iter()
+                .all(|cell| !matches!(cell.color, style::Color::Rgb { .. }))
+        );
+    }
+
+    #[test]
+    fn snapshot_restores_frame() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (40, 30));
+        for _ in 0..20 {
+            rain.update();
+        }
+        let snapshot = rain.snapshot();
+        let frame: Vec<_> = rain.frame().collect();
+        for _ in 0..20 {
+            rain.update();
+        }
+        assert_ne!(rain.frame().collect::<Vec<_>>(), frame);
+        rain.restore(snapshot);
+        assert_eq!(rain.frame().collect::<Vec<_>>(), frame);
+    }
+
+    #[test]
+    fn inserts_per_tick_within_budget() {
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((10, 10))
+            .speed_range((20, 20))
+            .max_inserts_per_tick(Some(3))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (20, 1000));
+        rain.rain_drops = (0..10)
+            .map(|i| {
+                RainDrop::from_values(
+                    i,
+                    vec!['a'],
+                    RainDropStyle::Front,
+                    i as u16,
+                    10.0,
+                    500,
+                    20,
+                )
+            })
+            .collect();
+        let total = |rain: &DigitalRain| -> usize {
+            rain.rain_drops.iter().map(|d| d.body.len()).sum()
+        };
+        for _ in 0..50 {
+            let before = total(&rain);
+            rain.tick(Duration::from_millis(200));
+            let inserted = total(&rain) - before;
+            assert!(inserted <= 3);
+        }
+        assert!(total(&rain) > 10);
+    }
+
+    #[test]
+    fn adjust_speed_scales_drops() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        rain.rain_drops = [5, 20, 150]
+            .iter()
+            .enumerate()
+            .map(|(i, speed)| {
+                RainDrop::from_values(
+                    i,
+                    vec!['a'],
+                    RainDropStyle::Front,
+                    0,
+                    10.0,
+                    10,
+                    *speed,
+                )
+            })
+            .collect();
+        rain.adjust_speed(2.0);
+        let speeds: Vec<u16> = rain.rain_drops.iter().map(|d| d.speed).collect();
+        assert_eq!(speeds, vec![10, 40, MAX_SPEED]);
+        assert_eq!(rain.options.speed_range, (20, 40));
+
+        rain.adjust_speed(0.0);
+        assert!(rain.rain_drops.iter().all(|d| d.speed == 1));
+    }
+
+    #[test]
+    fn encoded_frame_round_trip() {
+        use super::super::packed::apply_frame;
+
+        let mut options = get_sane_default_options();
+        options.splash = true;
+        let mut rain = DigitalRain::new(options, (40, 30));
+        let mut remote = rain.render();
+        for _ in 0..30 {
+            rain.update();
+            let bytes = rain.encode_frame();
+            apply_frame(&mut remote, &bytes).unwrap();
+            rain.get_diff();
+            assert!(remote.buffer == rain.buffer.buffer);
+        }
+
+        rain.set_monochrome(Some(gradient::Color { r: 0, g: 255, b: 0 }));
+        rain.options.color_support = ColorSupport::Ansi16;
+        rain.update();
+        apply_frame(&mut remote, &rain.encode_frame()).unwrap();
+        assert!(remote.buffer == rain.render().buffer);
+    }
+
+    #[test]
+    fn per_column_speed_is_shared() {
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((60, 60))
+            .speed_range((2, 30))
+            .per_column_speed(true)
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (10, 30));
+        for _ in 0..200 {
+            rain.update();
+            let mut speeds = HashMap::new();
+            for rain_drop in rain.rain_drops.iter() {
+                let speed =
+                    *speeds.entry(rain_drop.base_fx).or_insert(rain_drop.speed);
+                assert_eq!(speed, rain_drop.speed);
+            }
+        }
+    }
+
+    #[test]
+    fn clone_forks_identical_animation() {
+        let mut rain =
+            DigitalRain::new_seeded(get_sane_default_options(), (40, 30), 7);
+        for _ in 0..10 {
+            rain.update();
+        }
+        let mut fork = rain.clone();
+        for _ in 0..100 {
+            rain.update();
+            fork.update();
+            assert!(rain.render().buffer == fork.render().buffer);
+        }
+
+        let other =
+            DigitalRain::new_seeded(get_sane_default_options(), (40, 30), 7);
+        let same = DigitalRain::new_seeded(get_sane_default_options(), (40, 30), 7);
+        assert!(other.render().buffer == same.render().buffer);
+    }
+
+    #[test]
+    fn tick_until_steady_fills_screen() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (40, 30));
+        assert!(!rain.is_full());
+        let ticks = rain.tick_until_steady(Duration::from_millis(50), 1000);
+        assert!(ticks > 0 && ticks < 1000);
+        assert!(rain.is_full());
+        assert_eq!(rain.tick_until_steady(Duration::from_millis(50), 1000), 0);
+
+        let mut rain = DigitalRain::new(get_sane_default_options(), (40, 30));
+        assert_eq!(rain.tick_until_steady(Duration::from_millis(50), 3), 3);
+        assert!(!rain.is_full());
+    }
+
+    #[test]
+    fn reveal_converges_to_message() {
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((20, 30))
+            .speed_range((10, 20))
+            .reveal(Some(RevealOptions {
+                text: "WAKE UP\nNEO".to_string(),
+                anchor: (2, 5),
+                freeze_probability: 0.5,
+            }))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (12, 15));
+        assert!(!rain.is_revealed());
+        for _ in 0..3000 {
+            rain.update();
+        }
+        assert!(rain.is_revealed());
+        for (dx, glyph) in "WAKE".chars().enumerate() {
+            assert_eq!(rain.glyph_at(2 + dx as u16, 5), Some(glyph));
+        }
+        assert_eq!(rain.glyph_at(7, 5), Some('U'));
+        assert_eq!(rain.glyph_at(3, 6), Some('E'));
+    }
+
+    #[test]
+    fn lifetime_stats_distance() {
+        let options = DigitalRainOptionsBuilder::default()
+            .speed_range(DEFAULT_SPEED_RANGE)
+            .drops_range((3, 3))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (30, 200));
+        rain.rain_drops = [10, 15, 20]
+            .iter()
+            .enumerate()
+            .map(|(i, speed)| {
+                RainDrop::from_values(
+                    i,
+                    vec!['a'],
+                    RainDropStyle::Front,
+                    0,
+                    0.0,
+                    5,
+                    *speed,
+                )
+            })
+            .collect();
+        // 5 seconds of 10 + 15 + 20 cells per second
+        for _ in 0..100 {
+            rain.tick(Duration::from_millis(50));
+        }
+        let stats = rain.lifetime_stats();
+        assert!((stats.distance - 225.0).abs() < 0.01);
+        assert_eq!(stats.resets, 0);
+
+        for _ in 0..200 {
+            rain.tick(Duration::from_millis(50));
+        }
+        assert!(rain.lifetime_stats().resets > 0);
+    }
+
+    #[test]
+    fn center_bias_spawns_near_middle() {
+        let mut rng = rand::rng();
+        let columns: Vec<u16> = (0..100).collect();
+        let middle = |bias: ColumnBias, rng: &mut rand::rngs::ThreadRng| {
+            (0..10_000)
+                .filter(|_| (25..75).contains(&bias.pick(&columns, rng)))
+                .count()
+        };
+        let uniform = middle(ColumnBias::Uniform, &mut rng);
+        let center = middle(ColumnBias::Center, &mut rng);
+        let edges = middle(ColumnBias::Edges, &mut rng);
+        assert!(center > uniform + 1000);
+        assert!(edges + 1000 < uniform);
+    }
+
+    #[test]
+    fn hue_shift_down_the_stream() {
+        use super::super::draw::{to_hsv, to_rgb};
+
+        let mut options = get_sane_default_options();
+        options.hue_shift = 10.0;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'; 6],
+            RainDropStyle::Back,
+            0,
+            20.0,
+            10,
+            10,
+        )];
+        let buffer = rain.render();
+        let hue = |y: usize| {
+            let (r, g, b) = to_rgb(buffer.get(0, y).color);
+            to_hsv(r, g, b).0
+        };
+        // head is at row 20, tail at 15
+        assert!((hue(15) - hue(20) - 50.0).abs() < 3.0);
+    }
+
+    #[test]
+    fn hero_drop_is_brighter() {
+        use super::super::draw::to_rgb;
+
+        let mut rain = DigitalRain::new(get_sane_default_options(), (30, 30));
+        rain.rain_drops = (0..2)
+            .map(|i| {
+                RainDrop::from_values(
+                    i + 1,
+                    vec!['a'; 6],
+                    RainDropStyle::Back,
+                    i as u16,
+                    20.0,
+                    10,
+                    10,
+                )
+            })
+            .collect();
+        assert!(rain.mark_hero(2, HeroStyle::default()));
+        assert!(!rain.mark_hero(3, HeroStyle::default()));
+        let buffer = rain.render();
+        let brightness = |x: usize| -> u32 {
+            (15..=20)
+                .map(|y| {
+                    let (r, g, b) = to_rgb(buffer.get(x, y).color);
+                    r.max(g).max(b) as u32
+                })
+                .sum()
+        };
+        assert!(brightness(1) > brightness(0));
+    }
+
+    #[test]
+    fn update_all_matches_per_drop_loop() {
+        let dt = Duration::from_millis(50);
+        let mut rain =
+            DigitalRain::new_seeded(get_sane_default_options(), (100, 200), 3);
+        let mut manual = rain.clone();
+
+        for _ in 0..10 {
+            rain.using_own_rng(|rain, rng| rain.update_all(dt, rng));
+            for rain_drop in manual.rain_drops.iter_mut() {
+                let reset = rain_drop.update(
+                    manual.screen_size,
+                    &manual.options,
+                    dt,
+                    &mut manual.rng,
+                );
+                assert!(!reset);
+            }
+        }
+        assert_eq!(
+            rain.frame().collect::<Vec<_>>(),
+            manual.frame().collect::<Vec<_>>()
+        );
+        let positions = |rain: &DigitalRain| -> Vec<(u16, f32)> {
+            rain.rain_drops.iter().map(|d| (d.fx, d.fy)).collect()
+        };
+        assert_eq!(positions(&rain), positions(&manual));
+    }
+
+    #[test]
+    fn burn_in_marks_stay() {
+        let mut options = get_sane_default_options();
+        options.burn_in_rate = 0.2;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        for _ in 0..100 {
+            rain.tick(Duration::from_millis(50));
+        }
+        rain.rain_drops.clear();
+        let marks = |rain: &DigitalRain| {
+            let buffer = rain.render();
+            buffer.iter().filter(|cell| cell.symbol != ' ').count()
+        };
+        assert!(marks(&rain) > 0);
+
+        rain.clear_burn_in();
+        assert_eq!(marks(&rain), 0);
+    }
+
+    #[test]
+    fn reseed_gives_same_frames() {
+        let options = get_sane_default_options();
+        let mut first = DigitalRain::new_seeded(options.clone(), (40, 40), 1);
+        let mut second = DigitalRain::new(options, (40, 40));
+        second.tick(Duration::from_millis(50));
+
+        first.reseed(42);
+        second.reseed(42);
+        for _ in 0..20 {
+            assert_eq!(
+                first.frame().collect::<Vec<_>>(),
+                second.frame().collect::<Vec<_>>()
+            );
+            first.tick(Duration::from_millis(50));
+            second.tick(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn obstacles_never_get_glyphs() {
+        let (width, height) = (30, 30);
+        let mut rain =
+            DigitalRain::new(get_sane_default_options(), (width, height));
+        // box in the middle of the screen
+        let mask: Vec<bool> = (0..width * height)
+            .map(|i| {
+                (10..20).contains(&(i % width)) && (10..20).contains(&(i / width))
+            })
+            .collect();
+        rain.set_obstacle_mask(mask.clone(), width);
+        for _ in 0..100 {
+            rain.tick(Duration::from_millis(50));
+            for (x, y, _) in rain.frame() {
+                assert!(!mask[(y * width + x) as usize]);
+            }
+            assert_eq!(rain.visible_cell_count(), rain.frame().count());
+        }
+        let buffer = rain.render();
+        assert!((10..20).all(|x| buffer.get(x, 15).symbol == ' '));
+    }
+
+    #[test]
+    fn flag_grapheme_survives_render() {
+        let flag = "\u{1f1ef}\u{1f1f5}";
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((20, 30))
+            .speed_range((10, 20))
+            .graphemes(vec![flag.to_string()])
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options, (30, 30));
+        for _ in 0..20 {
+            rain.tick(Duration::from_millis(50));
+        }
+        let buffer = rain.render();
+        let mut cells = 0;
+        for (x, y, text) in rain.frame_graphemes() {
+            assert_eq!(text, flag);
+            let symbol = buffer.get(x as usize, y as usize).symbol;
+            assert_eq!(rain.options.glyph(symbol), flag);
+            cells += 1;
+        }
+        assert!(cells > 0);
+        // cells sent to the terminal are resolved the same way
+        let diff = rain.get_diff();
+        assert!(!diff.is_empty());
+        for (_, _, cell) in diff.iter().filter(|(.., cell)| cell.symbol != ' ') {
+            assert_eq!(TerminalEffect::cluster(&rain, cell.symbol), Some(flag));
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn grapheme_text_splits_clusters() {
+        let options = DigitalRainOptionsBuilder::default()
+            .drops_range((20, 30))
+            .speed_range((10, 20))
+            .grapheme_text("a\u{1f1ef}\u{1f1f5}e\u{301}")
+            .build()
+            .unwrap();
+        assert_eq!(options.graphemes, ["a", "\u{1f1ef}\u{1f1f5}", "e\u{301}"]);
+    }
+
+    #[test]
+    fn grapheme_codes_do_not_collide() {
+        let code = char::from_u32(GRAPHEME_BASE).unwrap();
+        let mut options = DigitalRainOptions {
+            graphemes: vec!["\u{1f1ef}\u{1f1f5}".to_string()],
+            ..Default::default()
+        };
+        assert!(options.cluster(code).is_some());
+        assert_eq!(options.cluster('a'), None);
+
+        options.tail_light_chars = Some(vec![code]);
+        assert_eq!(
+            options.validate(),
+            Err(DigitalRainOptionsError::ReservedCharacter(code))
+        );
+        options.tail_light_chars = None;
+
+        // characters of custom source are taken as they are
+        options.char_source = Some(SharedCharSource::new(move || code));
+        assert_eq!(options.cluster(code), None);
+    }
+
+    #[test]
+    fn set_size_keeps_columns() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (60, 30));
+        let columns = |rain: &DigitalRain| -> Vec<(usize, u16)> {
+            rain.rain_drops.iter().map(|d| (d.id(), d.fx)).collect()
+        };
+        let before = columns(&rain);
+        rain.set_size((20, 40));
+        assert_eq!(columns(&rain), before);
+        assert_eq!(rain.screen_size, (20, 40));
+        assert!(rain.columns.iter().all(|x| *x < 20));
+        let drawn = rain.render();
+        let drawn = drawn.iter().filter(|cell| **cell != Cell::default());
+        assert_eq!(rain.get_diff().len(), drawn.count());
+
+        // growing honours dead column rate for the opened columns
+        let mut options = get_sane_default_options();
+        options.dead_column_rate = 0.5;
+        let mut rain = DigitalRain::new(options, (20, 30));
+        rain.set_size((220, 30));
+        let opened = rain.columns.iter().filter(|x| **x >= 20).count();
+        assert!(opened > 50 && opened < 150);
+    }
+
+    #[test]
+    fn set_size_shrink_crops_layers() {
+        for mirror_x in [false, true] {
+            let mut options = get_sane_default_options();
+            options.burn_in_rate = 0.5;
+            options.splash = true;
+            options.mirror_x = mirror_x;
+            let mut rain = DigitalRain::new(options, (60, 40));
+            rain.set_obstacle_mask(vec![true; 60 * 40], 60);
+            for _ in 0..100 {
+                rain.tick(Duration::from_millis(50));
+            }
+            rain.reveal_cells = vec![(50, 30, 'x', true), (5, 5, 'y', true)];
+            assert!(rain.burn_in.keys().any(|(x, y)| *x >= 20 || *y >= 10));
+
+            rain.set_size((20, 10));
+            let buffer = rain.render();
+            assert_eq!(buffer.get_size(), (20, 10));
+            assert!(rain.burn_in.keys().all(|(x, y)| *x < 20 && *y < 10));
+            assert!(rain.splash_cells.iter().all(|(x, y)| *x < 20 && *y < 10));
+            assert_eq!(rain.reveal_cells, vec![(5, 5, 'y', true)]);
+            assert_eq!(rain.obstacles.len(), 20 * 10);
+            rain.tick(Duration::from_millis(50));
+            rain.get_diff();
+        }
+    }
+
+    #[test]
+    fn update_size_fits_columns() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (60, 30));
+        TerminalEffect::update_size(&mut rain, 20, 30);
+        assert!(rain.columns.iter().all(|x| *x < 20));
+
+        TerminalEffect::update_size(&mut rain, 80, 30);
+        assert!(rain.columns.iter().any(|x| *x >= 60));
+    }
+
+    #[test]
+    fn paused_frame_matches_frame() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (40, 40));
+        for _ in 0..30 {
+            rain.tick(Duration::from_millis(50));
+        }
+        let paused = rain.paused_frame();
+        let frame: HashSet<(u16, u16, char)> = rain.frame().collect();
+        let positions: HashSet<(u16, u16)> =
+            frame.iter().map(|(x, y, _)| (*x, *y)).collect();
+        assert_eq!(paused.len(), positions.len());
+        assert!(paused.iter().all(|cell| frame.contains(cell)));
+        assert_eq!(paused, rain.paused_frame());
+    }
+
+    #[test]
+    fn global_opacity_scales_cells() {
+        use super::super::draw::to_rgb;
+
+        let mut rain = DigitalRain::new(get_sane_default_options(), (40, 40));
+        for _ in 0..20 {
+            rain.tick(Duration::from_millis(50));
+        }
+        let full = rain.render();
+        rain.options.global_opacity = 0.3;
+        let faint = rain.render();
+        let mut cells = 0;
+        for (full, faint) in full.iter().zip(faint.iter()) {
+            assert_eq!(full.symbol, faint.symbol);
+            if *full == Cell::default() {
+                continue;
+            }
+            let (r, g, b) = to_rgb(full.color);
+            let scaled = to_rgb(faint.color);
+            let expected = |c: u8| (c as f32 * 0.3).round() as u8;
+            assert_eq!(scaled, (expected(r), expected(g), expected(b)));
+            cells += 1;
+        }
+        assert!(cells > 0);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn rgba_head_pixels() {
+        use super::super::draw::to_rgb;
+
+        let mut rain = DigitalRain::new(get_sane_default_options(), (20, 10));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'; 3],
+            RainDropStyle::Front,
+            4,
+            5.0,
+            10,
+            10,
+        )];
+        let (cell_width, cell_height) = (6, 12);
+        let pixels = rain.render_rgba((cell_width, cell_height));
+        let row_px = 20 * cell_width as usize;
+        assert_eq!(pixels.len(), row_px * 10 * cell_height as usize * 4);
+
+        let (r, g, b) = to_rgb(rain.render().get(4, 5).color);
+        for py in 5 * cell_height as usize..6 * cell_height as usize {
+            for px in 4 * cell_width as usize..5 * cell_width as usize {
+                let offset = (py * row_px + px) * 4;
+                assert_eq!(pixels[offset..offset + 4], [r, g, b, 255]);
+            }
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn rgba_keeps_colors_when_downgraded() {
+        for color_support in [ColorSupport::Ansi256, ColorSupport::Ansi16] {
+            let mut options = get_sane_default_options();
+            options.color_support = color_support;
+            let mut rain = DigitalRain::new(options, (20, 10));
+            rain.rain_drops = vec![RainDrop::from_values(
+                1,
+                vec!['a'; 3],
+                RainDropStyle::Solid,
+                4,
+                5.0,
+                10,
+                10,
+            )];
+            let pixels = rain.render_rgba((1, 1));
+            let offset = (5 * 20 + 4) * 4;
+            assert_eq!(pixels[offset..offset + 4], [0, 255, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn fast_forward_matches_ticks() {
+        let options = get_sane_default_options();
+        let mut forwarded = DigitalRain::new_seeded(options.clone(), (60, 40), 8);
+        let mut ticked = DigitalRain::new_seeded(options, (60, 40), 8);
+        forwarded.fast_forward(Duration::from_secs(1));
+        for _ in 0..10 {
+            ticked.tick(Duration::from_millis(100));
+        }
+        assert_eq!(
+            forwarded.frame().collect::<Vec<_>>(),
+            ticked.frame().collect::<Vec<_>>()
+        );
+        assert_eq!(forwarded.lifetime_stats(), ticked.lifetime_stats());
+
+        // leftover shorter than a step is not lost
+        forwarded.fast_forward(Duration::from_millis(150));
+        ticked.tick(Duration::from_millis(100));
+        ticked.tick(Duration::from_millis(50));
+        assert_eq!(
+            forwarded.frame().collect::<Vec<_>>(),
+            ticked.frame().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn heads_only_one_cell_per_drop() {
+        let mut options = get_sane_default_options();
+        options.heads_only = true;
+        options.ghost_head = true;
+        options.antialias_head = true;
+        let mut rain = DigitalRain::new(options, (40, 40));
+        for _ in 0..40 {
+            rain.tick(Duration::from_millis(50));
+            for rain_drop in rain.rain_drops.iter() {
+                let emitted = rain_drop
+                    .to_points_styled(&rain.options)
+                    .iter()
+                    .filter(|p| p.y < 40)
+                    .count();
+                let head_y = rain_drop.head_row(Rounding::Floor);
+                let head_visible = (0..40).contains(&head_y);
+                assert_eq!(emitted, head_visible as usize);
+            }
+            assert_eq!(rain.visible_cell_count(), rain.frame().count());
+        }
+    }
+
+    #[test]
+    fn palette_cycle_colors_drops_in_turn() {
+        let palette = vec![
+            gradient::Color { r: 255, g: 0, b: 0 },
+            gradient::Color { r: 0, g: 255, b: 0 },
+            gradient::Color { r: 0, g: 0, b: 255 },
+        ];
+        let mut options = get_sane_default_options();
+        options.palette_cycle = Some(palette.clone());
+        let mut rain = DigitalRain::new(options, (40, 40));
+        let colors: Vec<_> = (0..3)
+            .map(|_| {
+                let drop_id = rain.add_drop();
+                rain.rain_drops
+                    .iter()
+                    .find(|rain_drop| rain_drop.id() == drop_id)
+                    .unwrap()
+                    .palette_color
+            })
+            .collect();
+        // rotation continues from drops spawned by constructor
+        let start = palette
+            .iter()
+            .position(|color| Some(*color) == colors[0])
+            .unwrap();
+        for (index, color) in colors.into_iter().enumerate() {
+            assert_eq!(color, Some(palette[(start + index) % 3]));
+        }
+    }
+
+    #[test]
+    fn custom_characters_never_fall_back_to_default() {
+        let katakana = CharGroup::Katakana.chars();
+        let mut options = get_sane_default_options();
+        options.characters = vec!['0', '1'];
+        let mut rain = DigitalRain::new(options, (40, 40));
+        for _ in 0..100 {
+            rain.tick(Duration::from_millis(50));
+            for rain_drop in rain.rain_drops.iter() {
+                assert!(rain_drop.body.iter().all(|c| !katakana.contains(c)));
+            }
+            assert!(rain.frame().all(|(_, _, c)| !katakana.contains(&c)));
+        }
+    }
+
+    #[test]
+    fn tick_with_shared_rng_sequence() {
+        let dt = Duration::from_millis(50);
+        let mut first =
+            DigitalRain::new_seeded(get_sane_default_options(), (60, 40), 1);
+        let mut second = first.clone();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut rng_copy = rng.clone();
+        for _ in 0..50 {
+            first.tick_with_rng(dt, &mut rng);
+            second.tick_with_rng(dt, &mut rng_copy);
+            assert!(first.frame().eq(second.frame()));
+        }
+        // internal rng is not used
+        assert_eq!(
+            first.rng,
+            DigitalRain::new_seeded(get_sane_default_options(), (60, 40), 1).rng
+        );
+    }
+
+    #[test]
+    fn lane_spacing_keeps_drops_in_lanes() {
+        let mut options = get_sane_default_options();
+        options.lane_spacing = Some(3);
+        let mut rain = DigitalRain::new(options, (40, 40));
+        for _ in 0..100 {
+            rain.tick(Duration::from_millis(50));
+            for rain_drop in rain.rain_drops.iter() {
+                assert_eq!(rain_drop.fx % 3, 0);
+            }
+        }
+        rain.set_size((80, 40));
+        for _ in 0..100 {
+            rain.tick(Duration::from_millis(50));
+            assert!(rain.rain_drops.iter().all(|d| d.fx % 3 == 0));
+        }
+    }
+
+    #[test]
+    fn average_fps_hint_over_recent_ticks() {
+        let mut rain = DigitalRain::new(get_sane_default_options(), (40, 40));
+        assert_eq!(rain.average_fps_hint(), None);
+        for cost in [10, 20, 30, 20] {
+            rain.record_tick_cost(Duration::from_millis(cost));
+        }
+        assert!((rain.average_fps_hint().unwrap() - 50.0).abs() < 0.01);
+
+        // old costs leave the window
+        for _ in 0..TICK_COST_WINDOW {
+            rain.record_tick_cost(Duration::from_millis(4));
+        }
+        assert!((rain.average_fps_hint().unwrap() - 250.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn without_prefill_drops_spawn_with_head_only() {
+        let mut options = get_sane_default_options();
+        options.prefill = false;
+        let mut rain = DigitalRain::new(options, (40, 40));
+        assert!(rain.rain_drops.iter().all(|d| d.body.len() == 1));
+        for _ in 0..20 {
+            let drop_id = rain.add_drop();
+            let rain_drop = rain.rain_drops.iter().find(|d| d.id() == drop_id);
+            assert_eq!(rain_drop.unwrap().body.len(), 1);
+        }
+    }
+
+    #[test]
+    fn speed_for_rows_per_frame_round_trip() {
+        for (rows, fps) in [(0.5, 30.0), (1.0, 20.0), (0.25, 60.0)] {
+            let speed_range =
+                DigitalRainOptions::speed_for_rows_per_frame(rows, fps);
+            assert_eq!(speed_range.0, speed_range.1);
+            let rain_drop = RainDrop::from_values(
+                1,
+                vec!['a'],
+                RainDropStyle::Front,
+                0,
+                0.0,
+                4,
+                speed_range.0,
+            );
+            let frame = Duration::from_secs_f32(1.0 / fps);
+            assert!((rain_drop.vertical_advance(frame) - rows).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn burst_drops_leave_after_exit() {
+        let mut options = get_sane_default_options();
+        options.drops_range = (10, 10);
+        let mut rain = DigitalRain::new(options, (60, 30));
+        assert_eq!(rain.drop_count(), 10);
+
+        rain.burst(20);
+        assert_eq!(rain.drop_count(), 30);
+        rain.tick(Duration::from_millis(50));
+        assert_eq!(rain.drop_count(), 30);
+
+        for _ in 0..200 {
+            rain.tick(Duration::from_millis(50));
+        }
+        assert_eq!(rain.drop_count(), 10);
+        assert!(rain.rain_drops.iter().all(|d| !d.burst));
+    }
+
+    #[test]
+    fn restore_drops_burst_wave() {
+        let mut options = get_sane_default_options();
+        options.drops_range = (10, 10);
+        let mut rain = DigitalRain::new(options, (60, 30));
+        let snapshot = rain.snapshot();
+        rain.burst(40);
+        rain.restore(snapshot);
+        for _ in 0..20 {
+            rain.tick(Duration::from_millis(50));
+        }
+        assert_eq!(rain.drop_count(), 10);
+    }
+
+    #[test]
+    fn try_new_rejects_bad_size_and_options() {
+        assert_eq!(
+            DigitalRain::try_new(get_sane_default_options(), (0, 0)).err(),
+            Some(RainError::EmptyScreen(0, 0))
+        );
+        let mut options = get_sane_default_options();
+        options.speed_range = (20, 10);
+        assert_eq!(
+            DigitalRain::try_new(options, (40, 40)).err(),
+            Some(RainError::Options(
+                DigitalRainOptionsError::InvalidSpeedRange(20, 10)
+            ))
+        );
+        assert!(DigitalRain::try_new(get_sane_default_options(), (40, 40)).is_ok());
+    }
+
+    #[test]
+    fn rains_on_short_screens() {
+        for height in [1, 3, 5] {
+            let mut rain =
+                DigitalRain::try_new(get_sane_default_options(), (40, height))
+                    .unwrap();
+            for _ in 0..200 {
+                rain.tick(Duration::from_millis(50));
+            }
+            assert!(rain.drop_count() > 0);
+        }
+    }
+
+    #[test]
+    fn new_char_flashes_for_one_frame() {
+        let mut options = get_sane_default_options();
+        options.drops_range = (0, 0);
+        options.new_char_flash = true;
+        let mut rain = DigitalRain::new(options, (30, 30));
+        rain.rain_drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Fading,
+            5,
+            10.0,
+            10,
+            20,
+        )];
+        // one row per tick, so the drop grows a new head
+        rain.tick(Duration::from_millis(50));
+        assert_eq!(rain.rain_drops[0].length(), 2);
+        assert_eq!(rain.render().get(5, 11).color, FLASH_COLOR);
+
+        rain.tick(Duration::ZERO);
+        assert_eq!(rain.rain_drops[0].length(), 2);
+        assert_ne!(rain.render().get(5, 11).color, FLASH_COLOR);
+    }
+
+    #[test]
+    fn pool_rotation_shifts_group_mix() {
+        let mut options = get_sane_default_options();
+        options.pool_rotation = Some(Duration::from_secs(4));
+        let mut rain = DigitalRain::new(options, (40, 40));
+        let digits = CharGroup::Digits.chars();
+        let katakana = CharGroup::Katakana.chars();
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut shares = |rain: &DigitalRain| {
+            let (_, pool) = rain.rotated_pool.as_ref().unwrap();
+            let sample: Vec<char> = (0..10_000)
+                .map(|_| pool[rng.random_range(0..pool.len())])
+                .collect();
+            let share = |group: &[char]| {
+                sample.iter().filter(|c| group.contains(c)).count() as f32
+                    / sample.len() as f32
+            };
+            (share(&digits), share(&katakana))
+        };
+
+        rain.tick(Duration::ZERO);
+        let (digits_start, katakana_start) = shares(&rain);
+        // half of the period later katakana is at its peak
+        rain.fast_forward(Duration::from_secs(2));
+        let (digits_half, katakana_half) = shares(&rain);
+        assert!(digits_half < digits_start);
+        assert!(katakana_half > katakana_start);
+        assert!(rain.options.group_weights.is_empty());
+    }
+
     #[test]
     fn same_diff_and_update() {
         let mut foo = DigitalRain::new(get_sane_default_options(), (100, 100));
@@ -1,3 +1,4 @@
+use crate::rain::digital_rain::ColorSupport;
 use crate::rain::gradient;
 use crate::rain::rain_drop::RainDropStyle;
 use crossterm::style;
@@ -10,6 +11,7 @@ pub fn pick_style(vw_style: &RainDropStyle, pos: usize) -> style::Attribute {
             _ => style::Attribute::NormalIntensity,
         },
         RainDropStyle::Back => style::Attribute::Bold,
+        RainDropStyle::Solid => style::Attribute::Bold,
         _ => style::Attribute::NormalIntensity,
     }
 }
@@ -44,12 +46,222 @@ pub fn pick_color(
                 b: color.b,
             }
         }
+        RainDropStyle::Solid => style::Color::Rgb { r: 0, g: 255, b: 0 },
         _ => style::Color::DarkGrey,
     }
 }
 
+/// Bit of a dot inside of 2x4 Braille pattern, `dx` is 0..2 and `dy` is 0..4
+pub fn braille_dot(dx: u16, dy: u16) -> u8 {
+    match (dx, dy) {
+        (0, 3) => 0x40,
+        (1, 3) => 0x80,
+        (0, dy) => 1 << dy,
+        (_, dy) => 1 << (dy + 3),
+    }
+}
+
+/// Braille character with given dots set
+pub fn braille_char(dots: u8) -> char {
+    char::from_u32(0x2800 + dots as u32).unwrap()
+}
+
+/// Approximate RGB components of a crossterm color, palette colors
+/// are taken with their usual values
+pub fn to_rgb(color: style::Color) -> (u8, u8, u8) {
+    match color {
+        style::Color::Rgb { r, g, b } => (r, g, b),
+        style::Color::AnsiValue(value) => ansi_256_rgb(value),
+        _ => ANSI_16
+            .iter()
+            .find(|(named, _)| *named == color)
+            .map_or((0, 0, 0), |(_, rgb)| *rgb),
+    }
+}
+
+/// RGB components of a color from 256 colors palette, base colors
+/// followed by the color cube and the grayscale ramp
+fn ansi_256_rgb(value: u8) -> (u8, u8, u8) {
+    match value {
+        0..16 => ANSI_16[value as usize].1,
+        16..232 => {
+            let index = (value - 16) as usize;
+            (
+                CUBE_LEVELS[index / 36],
+                CUBE_LEVELS[index / 6 % 6],
+                CUBE_LEVELS[index % 6],
+            )
+        }
+        _ => {
+            let gray = 8 + 10 * (value - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// Multiply color brightness by intensity, saturating at max intensity
+pub fn scale_color(color: style::Color, intensity: f32) -> style::Color {
+    if intensity == 1.0 {
+        return color;
+    }
+    let (r, g, b) = to_rgb(color);
+    let scale = |c: u8| (c as f32 * intensity.max(0.0)).round().min(255.0) as u8;
+    style::Color::Rgb {
+        r: scale(r),
+        g: scale(g),
+        b: scale(b),
+    }
+}
+
+/// Replace hue of the color with tint, keeping its brightness
+pub fn tint_color(color: style::Color, tint: gradient::Color) -> style::Color {
+    let (r, g, b) = to_rgb(color);
+    let brightness = r.max(g).max(b) as f32 / 255.0;
+    scale_color(
+        style::Color::Rgb {
+            r: tint.r,
+            g: tint.g,
+            b: tint.b,
+        },
+        brightness,
+    )
+}
+
+/// Sum two colors channel by channel, saturating at max intensity
+pub fn add_colors(a: style::Color, b: style::Color) -> style::Color {
+    let (ar, ag, ab) = to_rgb(a);
+    let (br, bg, bb) = to_rgb(b);
+    style::Color::Rgb {
+        r: ar.saturating_add(br),
+        g: ag.saturating_add(bg),
+        b: ab.saturating_add(bb),
+    }
+}
+
+/// Hue in degrees, saturation and value of RGB color
+pub fn to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
+}
+
+/// RGB color from hue in degrees, saturation and value
+pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let chroma = value * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f32| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Rotate hue of the color by `degrees`, grays stay the same
+pub fn rotate_hue(color: style::Color, degrees: f32) -> style::Color {
+    if degrees == 0.0 {
+        return color;
+    }
+    let (r, g, b) = to_rgb(color);
+    let (hue, saturation, value) = to_hsv(r, g, b);
+    let (r, g, b) = from_hsv(hue + degrees, saturation, value);
+    style::Color::Rgb { r, g, b }
+}
+
+/// Channel levels of the 6x6x6 color cube in 256 colors palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Basic 16 colors with their usual RGB values
+const ANSI_16: [(style::Color, (u8, u8, u8)); 16] = [
+    (style::Color::Black, (0, 0, 0)),
+    (style::Color::DarkRed, (128, 0, 0)),
+    (style::Color::DarkGreen, (0, 128, 0)),
+    (style::Color::DarkYellow, (128, 128, 0)),
+    (style::Color::DarkBlue, (0, 0, 128)),
+    (style::Color::DarkMagenta, (128, 0, 128)),
+    (style::Color::DarkCyan, (0, 128, 128)),
+    (style::Color::Grey, (192, 192, 192)),
+    (style::Color::DarkGrey, (128, 128, 128)),
+    (style::Color::Red, (255, 0, 0)),
+    (style::Color::Green, (0, 255, 0)),
+    (style::Color::Yellow, (255, 255, 0)),
+    (style::Color::Blue, (0, 0, 255)),
+    (style::Color::Magenta, (255, 0, 255)),
+    (style::Color::Cyan, (0, 255, 255)),
+    (style::Color::White, (255, 255, 255)),
+];
+
+/// Squared distance between two RGB colors
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Index of the nearest color in 256 colors palette, only color cube
+/// and grayscale ramp are used since base colors vary between terminals
+pub fn to_ansi_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| CUBE_LEVELS[*i].abs_diff(c))
+            .unwrap()
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = (average.saturating_sub(8) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_step;
+    if distance((r, g, b), (gray, gray, gray)) < distance((r, g, b), cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Nearest of the basic 16 colors
+pub fn to_ansi_16(r: u8, g: u8, b: u8) -> style::Color {
+    ANSI_16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .unwrap()
+        .0
+}
+
+/// Convert RGB color into the nearest one terminal is able to show,
+/// non RGB colors are kept as is
+pub fn downgrade_color(color: style::Color, support: ColorSupport) -> style::Color {
+    match (color, support) {
+        (style::Color::Rgb { r, g, b }, ColorSupport::Ansi256) => {
+            style::Color::AnsiValue(to_ansi_256(r, g, b))
+        }
+        (style::Color::Rgb { r, g, b }, ColorSupport::Ansi16) => {
+            to_ansi_16(r, g, b)
+        }
+        _ => color,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::rain::digital_rain::{DigitalRain, DigitalRainOptionsBuilder};
 
     // use crossterm::terminal;
@@ -71,6 +283,70 @@ mod tests {
         let _ = crate::common::run_loop(&mut stdout, &mut digital_rain, Some(10));
     }
 
+    #[test]
+    fn downgrade_to_256_colors() {
+        assert_eq!(to_ansi_256(0, 255, 0), 46);
+        assert_eq!(to_ansi_256(255, 255, 255), 231);
+        assert_eq!(to_ansi_256(0, 0, 0), 16);
+        assert_eq!(to_ansi_256(128, 128, 128), 244);
+        assert_eq!(
+            downgrade_color(
+                style::Color::Rgb { r: 0, g: 200, b: 0 },
+                ColorSupport::Ansi256
+            ),
+            style::Color::AnsiValue(40)
+        );
+    }
+
+    #[test]
+    fn downgrade_to_16_colors() {
+        let green = style::Color::Rgb {
+            r: 10,
+            g: 240,
+            b: 5,
+        };
+        assert_eq!(
+            downgrade_color(green, ColorSupport::Ansi16),
+            style::Color::Green
+        );
+        assert_eq!(to_ansi_16(0, 110, 0), style::Color::DarkGreen);
+        assert_eq!(to_ansi_16(200, 200, 200), style::Color::Grey);
+        assert_eq!(downgrade_color(green, ColorSupport::TrueColor), green);
+    }
+
+    #[test]
+    fn palette_colors_to_rgb() {
+        assert_eq!(to_rgb(style::Color::AnsiValue(46)), (0, 255, 0));
+        assert_eq!(to_rgb(style::Color::AnsiValue(40)), (0, 215, 0));
+        assert_eq!(to_rgb(style::Color::AnsiValue(244)), (128, 128, 128));
+        assert_eq!(to_rgb(style::Color::AnsiValue(2)), (0, 128, 0));
+        assert_eq!(to_rgb(style::Color::DarkCyan), (0, 128, 128));
+        assert_eq!(to_rgb(style::Color::Reset), (0, 0, 0));
+        for value in 16..=255 {
+            let (r, g, b) = to_rgb(style::Color::AnsiValue(value));
+            assert_eq!(to_ansi_256(r, g, b), value);
+        }
+    }
+
+    #[test]
+    fn hue_rotation() {
+        let green = style::Color::Rgb { r: 0, g: 200, b: 0 };
+        assert_eq!(rotate_hue(green, 0.0), green);
+        assert_eq!(
+            rotate_hue(green, 120.0),
+            style::Color::Rgb { r: 0, g: 0, b: 200 }
+        );
+        assert_eq!(
+            rotate_hue(green, -120.0),
+            style::Color::Rgb { r: 200, g: 0, b: 0 }
+        );
+        let (hue, ..) = to_hsv(0, 200, 0);
+        let style::Color::Rgb { r, g, b } = rotate_hue(green, 30.0) else {
+            unreachable!()
+        };
+        assert!((to_hsv(r, g, b).0 - hue - 30.0).abs() < 1.0);
+    }
+
     /* // NOTE: this test failed on github CI pipeline
     #[test]
     fn run_loop_fps_gte_0() {
@@ -2,7 +2,9 @@ fn lerp(a: u8, b: u8, t: f32) -> u8 {
     (a as f32 * (1.0 - t) + b as f32 * t).round() as u8
 }
 
-#[derive(Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -1,4 +1,13 @@
+#[cfg(feature = "std")]
 pub mod digital_rain;
+#[cfg(feature = "std")]
 pub mod draw;
+#[cfg(feature = "std")]
 pub mod gradient;
+#[cfg(feature = "std")]
+pub mod packed;
+#[cfg(feature = "std")]
 pub mod rain_drop;
+#[cfg(feature = "image")]
+pub mod raster;
+pub mod sim;
@@ -0,0 +1,184 @@
+//! Compact binary form of changed cells to send frames over the wire.
+//!
+//! Every cell is packed as varint `x`, varint `y`, varint glyph code point,
+//! style byte and color bytes. Style byte keeps attribute in its low bits
+//! and color kind in the next ones, color payload depends on the kind:
+//! named color index, RGB triple, 256 colors index or nothing for reset.
+use crate::buffer::{Buffer, Cell};
+use crate::error::PackedFrameError;
+use crossterm::style;
+
+/// Named colors in the order of their wire indexes
+const NAMED_COLORS: [style::Color; 16] = [
+    style::Color::Black,
+    style::Color::DarkRed,
+    style::Color::DarkGreen,
+    style::Color::DarkYellow,
+    style::Color::DarkBlue,
+    style::Color::DarkMagenta,
+    style::Color::DarkCyan,
+    style::Color::Grey,
+    style::Color::DarkGrey,
+    style::Color::Red,
+    style::Color::Green,
+    style::Color::Yellow,
+    style::Color::Blue,
+    style::Color::Magenta,
+    style::Color::Cyan,
+    style::Color::White,
+];
+
+const ATTR_RESET: u8 = 0;
+const ATTR_BOLD: u8 = 1;
+const ATTR_NORMAL: u8 = 2;
+const ATTR_MASK: u8 = 0b11;
+
+const COLOR_NAMED: u8 = 0 << 2;
+const COLOR_RGB: u8 = 1 << 2;
+const COLOR_ANSI: u8 = 2 << 2;
+const COLOR_RESET: u8 = 3 << 2;
+const COLOR_MASK: u8 = 0b11 << 2;
+
+/// Pack cells into bytes
+pub fn encode_cells(cells: &[(usize, usize, Cell)]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for (x, y, cell) in cells {
+        write_varint(&mut bytes, *x as u32);
+        write_varint(&mut bytes, *y as u32);
+        write_varint(&mut bytes, cell.symbol as u32);
+
+        let attr = match cell.attr {
+            style::Attribute::Bold => ATTR_BOLD,
+            style::Attribute::NormalIntensity => ATTR_NORMAL,
+            _ => ATTR_RESET,
+        };
+        match cell.color {
+            style::Color::Rgb { r, g, b } => {
+                bytes.extend([attr | COLOR_RGB, r, g, b]);
+            }
+            style::Color::AnsiValue(value) => {
+                bytes.extend([attr | COLOR_ANSI, value]);
+            }
+            style::Color::Reset => bytes.push(attr | COLOR_RESET),
+            named => {
+                let index = NAMED_COLORS.iter().position(|c| *c == named);
+                bytes.extend([attr | COLOR_NAMED, index.unwrap_or(0) as u8]);
+            }
+        }
+    }
+    bytes
+}
+
+/// Unpack cells from bytes and put them into the buffer,
+/// cells outside of the buffer are skipped
+pub fn apply_frame(
+    buffer: &mut Buffer,
+    bytes: &[u8],
+) -> Result<(), PackedFrameError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    while reader.pos < bytes.len() {
+        let x = reader.varint()? as usize;
+        let y = reader.varint()? as usize;
+        let code = reader.varint()?;
+        let symbol =
+            char::from_u32(code).ok_or(PackedFrameError::InvalidGlyph(code))?;
+
+        let style_byte = reader.byte()?;
+        let attr = match style_byte & ATTR_MASK {
+            ATTR_BOLD => style::Attribute::Bold,
+            ATTR_NORMAL => style::Attribute::NormalIntensity,
+            _ => style::Attribute::Reset,
+        };
+        let color = match style_byte & COLOR_MASK {
+            COLOR_RGB => style::Color::Rgb {
+                r: reader.byte()?,
+                g: reader.byte()?,
+                b: reader.byte()?,
+            },
+            COLOR_ANSI => style::Color::AnsiValue(reader.byte()?),
+            COLOR_RESET => style::Color::Reset,
+            _ => {
+                let index = reader.byte()?;
+                *NAMED_COLORS
+                    .get(index as usize)
+                    .ok_or(PackedFrameError::InvalidColor(index))?
+            }
+        };
+
+        if x < buffer.width && y < buffer.height {
+            buffer.set(x, y, Cell::new(symbol, color, attr));
+        }
+    }
+    Ok(())
+}
+
+/// Write LEB128 unsigned varint
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, PackedFrameError> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or(PackedFrameError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u32, PackedFrameError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PackedFrameError::VarintOverflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 300, 0x10ffff, u32::MAX] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, value);
+            let mut reader = Reader {
+                bytes: &bytes,
+                pos: 0,
+            };
+            assert_eq!(reader.varint(), Ok(value));
+            assert_eq!(reader.pos, bytes.len());
+        }
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let cells = vec![(
+            1,
+            2,
+            Cell::new('a', style::Color::AnsiValue(46), style::Attribute::Bold),
+        )];
+        let bytes = encode_cells(&cells);
+        let mut buffer = Buffer::new(4, 4);
+        assert_eq!(
+            apply_frame(&mut buffer, &bytes[..bytes.len() - 1]),
+            Err(PackedFrameError::UnexpectedEnd)
+        );
+    }
+}
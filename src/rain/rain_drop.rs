@@ -1,11 +1,20 @@
 // use super::rain_options::DigitalRainOptions;
-use crate::rain::digital_rain::DigitalRainOptions;
+use crate::error::{CharGroupError, RainDropStyleError};
+use crate::rain::digital_rain::{
+    DigitalRainOptions, HeroStyle, InitBody, Rounding, TailDirection,
+};
+use crate::rain::gradient;
+use crate::rain::sim::{self, DropPhase};
 use rand::{
-    self, Rng,
+    self, Rng, RngCore,
     distr::{Distribution, StandardUniform},
     seq::IndexedRandom,
 };
-use std::sync::LazyLock;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::{collections::HashMap, time::Duration};
 
 /// Characters in form of hashmap with label as key
@@ -13,7 +22,7 @@ use std::{collections::HashMap, time::Duration};
 /// screen in strange way.
 static CHARACTERS_MAP: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
     let mut m = HashMap::new();
-    m.insert("digits", "012345789");
+    m.insert("digits", "0123456789");
     // m.insert("punctuation", r#":・."=*+-<>"#); // wide character there
     m.insert("punctuation", r#":."=*+-<>"#);
     // m.insert("kanji", "日"); // wide character there
@@ -23,303 +32,2646 @@ static CHARACTERS_MAP: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
 });
 
 /// Characters used to form kinda-canonical matrix effect
+/// Groups are taken in fixed order, so seeded rains pick same characters.
+/// Read only through `default_characters`, drops take theirs from options
 static CHARACTERS: LazyLock<Vec<char>> = LazyLock::new(|| {
     let mut v = Vec::new();
-    for (_, chars) in CHARACTERS_MAP.iter() {
-        v.append(&mut chars.chars().collect());
+    for group in CharGroup::ALL {
+        v.append(&mut group.chars());
     }
     v
 });
 
+/// Characters drops are made of when no other set is configured
+#[inline]
+pub fn default_characters() -> &'static [char] {
+    CHARACTERS.as_slice()
+}
+
+/// Whether glyph takes two terminal cells, covers CJK, Hangul,
+/// fullwidth forms and emoji blocks
+pub fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115f
+            | 0x2e80..=0x303e
+            | 0x3041..=0x33ff
+            | 0x3400..=0x4dbf
+            | 0x4e00..=0x9fff
+            | 0xa000..=0xa4cf
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x1f300..=0x1f64f
+            | 0x1f900..=0x1f9ff
+            | 0x20000..=0x3fffd
+    )
+}
+
+/// Named group of characters, one per `CHARACTERS_MAP` key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharGroup {
+    Digits,
+    Punctuation,
+    Katakana,
+    Other,
+}
+
+impl CharGroup {
+    pub const ALL: [CharGroup; 4] = [
+        CharGroup::Digits,
+        CharGroup::Punctuation,
+        CharGroup::Katakana,
+        CharGroup::Other,
+    ];
+
+    /// Label of the group in `CHARACTERS_MAP`
+    pub fn key(&self) -> &'static str {
+        match self {
+            CharGroup::Digits => "digits",
+            CharGroup::Punctuation => "punctuation",
+            CharGroup::Katakana => "katakana",
+            CharGroup::Other => "other",
+        }
+    }
+
+    /// Characters of the group
+    pub fn chars(&self) -> Vec<char> {
+        CHARACTERS_MAP[self.key()].chars().collect()
+    }
+}
+
+impl FromStr for CharGroup {
+    type Err = CharGroupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CharGroup::ALL
+            .into_iter()
+            .find(|group| group.key() == s)
+            .ok_or_else(|| CharGroupError::Unknown(s.to_string()))
+    }
+}
+
+impl TryFrom<&str> for CharGroup {
+    type Error = CharGroupError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Picks characters drops are made of instead of the default uniform picker
+pub trait CharSource {
+    /// Next character for a drop of `style`, `index` counts characters
+    /// the drop got since spawn, head of fresh drop is 0
+    fn next_char(
+        &mut self,
+        rng: &mut dyn RngCore,
+        style: RainDropStyle,
+        index: usize,
+    ) -> char;
+}
+
+/// Closures ignoring drop style and index are sources too
+impl<F: FnMut() -> char> CharSource for F {
+    fn next_char(
+        &mut self,
+        _rng: &mut dyn RngCore,
+        _style: RainDropStyle,
+        _index: usize,
+    ) -> char {
+        self()
+    }
+}
+
+/// Character source shared between option copies, clones call
+/// the same source and compare equal to each other only
+#[derive(Clone)]
+pub struct SharedCharSource(Arc<Mutex<dyn CharSource + Send>>);
+
+impl SharedCharSource {
+    pub fn new(source: impl CharSource + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(source)))
+    }
+
+    fn next_char(
+        &self,
+        rng: &mut dyn RngCore,
+        style: RainDropStyle,
+        index: usize,
+    ) -> char {
+        let mut source = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        source.next_char(rng, style, index)
+    }
+}
+
+impl fmt::Debug for SharedCharSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedCharSource")
+    }
+}
+
+impl PartialEq for SharedCharSource {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Unsized rng passed to `CharSource` as trait object
+struct RngRef<'a, R: ?Sized>(&'a mut R);
+
+impl<R: RngCore + ?Sized> RngCore for RngRef<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.0.fill_bytes(dst)
+    }
+}
+
+/// First private use code point standing for clusters of `graphemes` option,
+/// body character `GRAPHEME_BASE + i` is drawn as i-th cluster
+pub const GRAPHEME_BASE: u32 = 0xf0000;
+
+/// Body cell left empty by `body_fill`, never drawn
+pub const BLANK: char = '\0';
+
+/// Brightness of the ghost head relative to the head
+const GHOST_INTENSITY: f32 = 0.4;
+
+/// Body length drops get on reset with `instant_respawn`
+const RESPAWN_LENGTH: usize = 3;
+
+/// Part of the body from the head after which `tail_light_chars` start
+const LIGHT_TAIL_START: f32 = 0.5;
+
+/// Shortest and longest time ephemeral drops live before fading out
+const EPHEMERAL_LIFESPAN: (Duration, Duration) =
+    (Duration::from_millis(500), Duration::from_secs(3));
+
+/// Time ephemeral drops take to fade out completely
+const EPHEMERAL_FADE: Duration = Duration::from_millis(500);
+
+/// Number of rows at the top edge where clipped tails dissolve
+const TOP_FADE_ROWS: u16 = 3;
+
+/// Slowest fraction of own speed drops keep inside of ease-out zone
+const EASE_OUT_MIN: f32 = 0.2;
+
+/// Number of random values generated per refill of `CharBatch`
+const CHAR_BATCH_SIZE: usize = 32;
+
+/// Random values pre-generated in bulk to pick characters from,
+/// so drops inserting a lot of characters do not call rng per character
+#[derive(Clone)]
+struct CharBatch {
+    values: [u32; CHAR_BATCH_SIZE],
+    next: usize,
+}
+
+impl Default for CharBatch {
+    /// Batch starts exhausted and gets filled on first pick
+    fn default() -> Self {
+        Self {
+            values: [0; CHAR_BATCH_SIZE],
+            next: CHAR_BATCH_SIZE,
+        }
+    }
+}
+
+impl CharBatch {
+    fn pick<R: Rng + ?Sized>(&mut self, characters: &[char], rng: &mut R) -> char {
+        if self.next == CHAR_BATCH_SIZE {
+            rng.fill(&mut self.values);
+            self.next = 0;
+        }
+        let value = self.values[self.next];
+        self.next += 1;
+        characters[value as usize % characters.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RainDropStyle {
     Front,
     Middle,
     Back,
     Fading,
     Gradient,
+    /// Flat stream with every cell at full brightness
+    Solid,
 }
 
+/// Chance of each style in the order of `RainDropStyle::ALL`, in percents
+pub const DEFAULT_STYLE_WEIGHTS: [u32; 6] = [10, 10, 20, 10, 45, 5];
+
+#[derive(Clone)]
 pub struct RainDrop {
-    pub _drop_id: usize,
+    id: usize,
     pub body: Vec<char>,
     pub style: RainDropStyle,
     pub fx: u16,
+    /// Column the drop wiggles around, same as `fx` for straight drops
+    pub base_fx: u16,
     pub fy: f32,
     pub max_length: usize,
     pub speed: u16,
+    /// Index into `per_drop_charsets` option the drop takes characters from
+    pub charset: Option<usize>,
+    /// Number of cells cut from the head after they left the screen bottom
+    trimmed: usize,
+    /// Characters not inserted yet because of the insert budget
+    pending_growth: usize,
+    /// Shift of the tail floor option for this drop
+    floor_jitter: f32,
+    /// Head character is taken from the rare set and moves along with head
+    rare_head: bool,
+    /// Emphasis the drop is drawn with, kept across resets
+    pub hero: Option<HeroStyle>,
+    /// Color from `palette_cycle` option the drop is tinted with
+    pub palette_color: Option<gradient::Color>,
+    /// Drop is a part of burst wave and is removed instead of reset
+    pub burst: bool,
+    /// Time left before the drop starts falling
+    start_delay: Duration,
+    /// Time between character swaps of the drop, zero if it never flickers
+    flicker_interval: Duration,
+    /// Time passed since the last character swap
+    flicker_elapsed: Duration,
+    /// Characters the drop is made of, subset of the character pool or
+    /// the pool itself when it is built by filtering. Options pool if empty
+    glyphs: Vec<char>,
+    /// Time ephemeral drop lives before fading out, `None` for regular drops
+    lifespan: Option<Duration>,
+    /// Time passed since spawn or the last reset
+    age: Duration,
+    /// Ticks passed since a character was inserted at the head
+    head_age: u32,
+    char_batch: CharBatch,
+}
+
+impl RainDropStyle {
+    pub const ALL: [RainDropStyle; 6] = [
+        RainDropStyle::Front,
+        RainDropStyle::Middle,
+        RainDropStyle::Back,
+        RainDropStyle::Fading,
+        RainDropStyle::Gradient,
+        RainDropStyle::Solid,
+    ];
+
+    /// Pick random style using default weights
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::random_weighted(&DEFAULT_STYLE_WEIGHTS, rng)
+    }
+
+    /// Pick random style with default weights among `allowed` ones,
+    /// any style can be picked if `allowed` is empty
+    pub fn random_allowed<R: Rng + ?Sized>(
+        allowed: &[RainDropStyle],
+        rng: &mut R,
+    ) -> Self {
+        if allowed.is_empty() {
+            return Self::random(rng);
+        }
+        let mut weights = DEFAULT_STYLE_WEIGHTS;
+        for (weight, style) in weights.iter_mut().zip(Self::ALL) {
+            if !allowed.contains(&style) {
+                *weight = 0;
+            }
+        }
+        Self::random_weighted(&weights, rng)
+    }
+
+    /// Pick random style, weights are given in the order of `ALL`.
+    /// Falls back to default weights if all of them are zero
+    pub fn random_weighted<R: Rng + ?Sized>(
+        weights: &[u32; 6],
+        rng: &mut R,
+    ) -> Self {
+        let total: u32 = weights.iter().sum();
+        if total == 0 {
+            return Self::random(rng);
+        }
+        let mut roll = rng.random_range(0..total);
+        for (style, weight) in Self::ALL.iter().zip(weights) {
+            if roll < *weight {
+                return *style;
+            }
+            roll -= weight;
+        }
+        RainDropStyle::Gradient
+    }
+}
+
+impl RainDropStyle {
+    /// Lowercase name of the style
+    pub fn name(&self) -> &'static str {
+        match self {
+            RainDropStyle::Front => "front",
+            RainDropStyle::Middle => "middle",
+            RainDropStyle::Back => "back",
+            RainDropStyle::Fading => "fading",
+            RainDropStyle::Gradient => "gradient",
+            RainDropStyle::Solid => "solid",
+        }
+    }
+}
+
+impl fmt::Display for RainDropStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RainDropStyle {
+    type Err = RainDropStyleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RainDropStyle::ALL
+            .into_iter()
+            .find(|style| style.name() == s)
+            .ok_or_else(|| RainDropStyleError::Unknown(s.to_string()))
+    }
 }
 
 impl Distribution<RainDropStyle> for StandardUniform {
     /// Choose from range
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> RainDropStyle {
-        match rng.random_range(1..=100) {
-            1..=10 => RainDropStyle::Front,
-            11..=20 => RainDropStyle::Middle,
-            21..=40 => RainDropStyle::Back,
-            41..=50 => RainDropStyle::Fading,
-            _ => RainDropStyle::Gradient,
-        }
+        RainDropStyle::random(rng)
     }
 }
 
+/// Single drawable cell of a rain drop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StyledPoint {
+    pub x: u16,
+    pub y: u16,
+    pub character: char,
+    /// Position in the drop body, 0 is the head
+    pub index: usize,
+    /// Position in the style color gradient, 0 is the brightest
+    pub shade: usize,
+    /// Brightness multiplier on top of style color, 1.0 keeps it unchanged
+    pub intensity: f32,
+    /// Hue rotation of style color in degrees, 0.0 keeps it unchanged
+    pub hue: f32,
+}
+
 /// Set of operations to make drain drop moving and growing
 impl RainDrop {
     /// Create new rain drop with sane random defaults
-    pub fn new(
+    pub fn new<R: Rng + ?Sized>(
         screen_size: (u16, u16),
         options: &DigitalRainOptions,
         drop_id: usize,
-        rng: &mut rand::prelude::ThreadRng,
+        rng: &mut R,
     ) -> Self {
         // pick random first character
-        let style: RainDropStyle = rand::random();
-        let fx: u16 = rng.random_range(0..screen_size.0);
-        let fy: f32 = rng.random_range(0..screen_size.1 / 4) as f32;
-        let max_length: usize =
-            rng.random_range(4..=(2 * screen_size.1 / 3)) as usize;
+        let style = RainDropStyle::random_allowed(&options.allowed_styles, rng);
+        let fx = options.snap_to_lane(rng.random_range(0..screen_size.0));
+        let fy: f32 = match options.stagger {
+            true => {
+                sim::stagger_row(drop_id, sim::start_band(screen_size.1)) as f32
+            }
+            false => sim::start_row(screen_size.1, rng) as f32,
+        };
+        let max_length = match options.length_range {
+            Some(range) => sim::pick_length(range, rng),
+            None => sim::spawn_length(screen_size.1, rng),
+        };
 
         let speed: u16 =
             rng.random_range(options.get_min_speed()..=options.get_max_speed());
 
-        let init_length = rng.random_range(1..max_length / 2);
-        let mut body: Vec<char> = vec![*CHARACTERS.choose(rng).unwrap()];
-        for _ in 1..init_length {
-            body.push(*CHARACTERS.choose(rng).unwrap());
+        // short drops start with the head only, as all do without prefill
+        let init_length = match max_length / 2 {
+            _ if !options.prefill => 1,
+            0 | 1 => 1,
+            half => rng.random_range(1..half),
+        };
+        let mut rain_drop =
+            Self::from_values(drop_id, vec![], style, fx, fy, max_length, speed);
+        rain_drop.charset = Self::pick_charset(options, rng);
+        rain_drop.pick_glyphs(options, rng);
+        let head = rain_drop.random_char(options, rng);
+        rain_drop.body.push(head);
+        for index in 1..init_length {
+            let row = fy as i32 - index as i32;
+            let character = match options.init_body_mode {
+                InitBody::Random => rain_drop.random_char_at(
+                    options,
+                    row,
+                    options.char_source.as_ref(),
+                    rng,
+                ),
+                InitBody::RepeatHead => head,
+            };
+            rain_drop.body.push(character);
+        }
+        rain_drop.roll_rare_head(options, rng);
+        rain_drop.roll_floor_jitter(options, rng);
+        rain_drop.roll_start_delay(options, rng);
+        rain_drop.roll_flicker_interval(options, rng);
+        rain_drop.roll_lifespan(options, rng);
+        rain_drop
+    }
+
+    /// Create new worm from values
+    #[inline(always)]
+    pub fn from_values(
+        id: usize,
+        body: Vec<char>,
+        style: RainDropStyle,
+        fx: u16,
+        fy: f32,
+        max_length: usize,
+        speed: u16,
+    ) -> Self {
+        Self {
+            id,
+            body,
+            style,
+            fx,
+            base_fx: fx,
+            fy,
+            max_length,
+            speed,
+            charset: None,
+            trimmed: 0,
+            pending_growth: 0,
+            floor_jitter: 0.0,
+            rare_head: false,
+            hero: None,
+            palette_color: None,
+            burst: false,
+            start_delay: Duration::ZERO,
+            flicker_interval: Duration::ZERO,
+            flicker_elapsed: Duration::ZERO,
+            glyphs: vec![],
+            lifespan: None,
+            age: Duration::ZERO,
+            head_age: 0,
+            char_batch: CharBatch::default(),
+        }
+    }
+
+    /// Number of cells in the drop body
+    #[inline]
+    pub fn length(&self) -> usize {
+        self.body.len()
+    }
+
+    /// Stable identity of the drop, kept across resets
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Copy of the drop with the same body, style and speed placed
+    /// at another position under new id, handy to split streams
+    pub fn clone_at(&self, fx: u16, fy: f32, id: usize) -> RainDrop {
+        let mut rain_drop = self.clone();
+        rain_drop.id = id;
+        rain_drop.set_column(fx);
+        rain_drop.fy = fy;
+        // the copy draws own characters and does not owe any growth
+        rain_drop.char_batch = CharBatch::default();
+        rain_drop.pending_growth = 0;
+        rain_drop
+    }
+
+    /// Pick random character for the drop body, `char_source` picks it if set
+    #[inline]
+    pub fn random_char<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) -> char {
+        let row = self.head_row(options.cell_rounding);
+        self.random_char_at(options, row, options.char_source.as_ref(), rng)
+    }
+
+    /// Pick random character for body cell drawn on `row`, `source` picks
+    /// it if set. Screen region set of the row wins over other sets
+    fn random_char_at<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        row: i32,
+        source: Option<&SharedCharSource>,
+        rng: &mut R,
+    ) -> char {
+        if let Some(source) = source {
+            let index = self.body.len() + self.trimmed;
+            return source.next_char(&mut RngRef(rng), self.style, index);
+        }
+        if !options.graphemes.is_empty() {
+            let index = rng.random_range(0..options.graphemes.len()) as u32;
+            return char::from_u32(GRAPHEME_BASE + index).unwrap_or(BLANK);
+        }
+        if let Some(characters) = options.region_charset(row) {
+            return self.char_batch.pick(characters, rng);
+        }
+        if !self.glyphs.is_empty() {
+            return self.char_batch.pick(&self.glyphs, rng);
+        }
+        let characters = options.character_pool(self.charset, self.style);
+        self.char_batch.pick(&characters, rng)
+    }
+
+    /// Pick subset of the character pool the drop is limited to
+    fn pick_glyphs<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
+        self.glyphs.clear();
+        let pool = options.character_pool(self.charset, self.style);
+        match (options.glyphs_per_drop, pool) {
+            (Some(amount), pool) => {
+                self.glyphs.extend(pool.choose_multiple(rng, amount.max(1)));
+            }
+            // filtered pool is kept, so it is not rebuilt for every character
+            (None, Cow::Owned(pool)) => self.glyphs = pool,
+            (None, Cow::Borrowed(_)) => {}
+        }
+    }
+
+    /// Pick glyphs as on spawn with `default_pool` in place of default
+    /// characters, the drop keeps the pool until it is picked again
+    pub fn pick_glyphs_from<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        default_pool: &[char],
+        rng: &mut R,
+    ) {
+        self.glyphs.clear();
+        let pool =
+            options.character_pool_or(self.charset, self.style, default_pool);
+        match options.glyphs_per_drop {
+            Some(amount) => {
+                self.glyphs.extend(pool.choose_multiple(rng, amount.max(1)));
+            }
+            None => self.glyphs = pool.into_owned(),
+        }
+    }
+
+    /// Replace head with one of rare glyphs with chance set by options
+    fn roll_rare_head<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
+        self.rare_head = false;
+        if let Some((chance, glyphs)) = options.rare_head.as_ref()
+            && let Some(glyph) = glyphs.choose(rng)
+            && rng.random_bool(chance.clamp(0.0, 1.0) as f64)
+            && let Some(head) = self.body.first_mut()
+        {
+            *head = *glyph;
+            self.rare_head = true;
+        }
+    }
+
+    /// Pick one of per drop character sets if there are any
+    fn pick_charset<R: Rng + ?Sized>(
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) -> Option<usize> {
+        match options.per_drop_charsets.len() {
+            0 => None,
+            len => Some(rng.random_range(0..len)),
+        }
+    }
+
+    /// Move drop into `x` column
+    #[inline]
+    pub fn set_column(&mut self, x: u16) {
+        self.fx = x;
+        self.base_fx = x;
+    }
+
+    /// Shift drop from its base column along sine path of its `fy`
+    fn wiggle(&mut self, options: &DigitalRainOptions, width: u16) {
+        if let Some(wiggle) = options.wiggle {
+            // amplitude is in rows, cells are narrower than tall
+            let amplitude = wiggle.amplitude * options.aspect_ratio;
+            let offset = amplitude * (wiggle.frequency * self.fy).sin();
+            let x = (self.base_fx as f32 + offset).round();
+            self.fx = options.horizontal_edge.apply(x, width);
+        }
+    }
+
+    /// Convert float into screen coordinates
+    #[inline]
+    pub fn to_point(&self) -> (u16, u16) {
+        self.to_point_with(Rounding::Round)
+    }
+
+    /// Convert float into screen coordinates with given rounding
+    #[inline]
+    pub fn to_point_with(&self, rounding: Rounding) -> (u16, u16) {
+        let x = self.fx;
+        // float to int `as` cast saturates, so huge fy never wraps around
+        let y = rounding.apply(self.fy) as u16;
+        (x, y)
+    }
+
+    /// Row of the head with given rounding, negative while the drop
+    /// is above the screen
+    #[inline]
+    pub fn head_row(&self, rounding: Rounding) -> i32 {
+        (rounding.apply(self.fy) as i32).min(u16::MAX as i32)
+    }
+
+    /// Receive vector of coordinates of RainDrop body, every cell regardless
+    /// of options and head on the nearest row, see `to_points` for cells
+    /// actually drawn
+    pub fn to_points_vec(&self) -> Vec<(u16, u16, char)> {
+        self.to_points_vec_with(Rounding::Round)
+    }
+
+    /// Receive vector of coordinates of RainDrop body with given rounding
+    pub fn to_points_vec_with(&self, rounding: Rounding) -> Vec<(u16, u16, char)> {
+        self.points_from(self.head_row(rounding))
+            .into_iter()
+            .map(|(_, x, y, character)| (x, y, character))
+            .collect()
+    }
+
+    /// Coordinates of body cells drawn with `options`, head row follows
+    /// `cell_rounding` and gaps left by `char_spacing` are skipped.
+    /// Cells are not shaded, see `to_points_styled` for full styling
+    pub fn to_points(&self, options: &DigitalRainOptions) -> Vec<(u16, u16, char)> {
+        let spacing = options.char_spacing.max(1) as usize;
+        self.points_from(self.drawn_head_row(options))
+            .into_iter()
+            .filter(|(index, ..)| index.is_multiple_of(spacing))
+            .map(|(_, x, y, character)| (x, y, character))
+            .collect()
+    }
+
+    /// Body coordinates with body index when head is placed at `head_y` row,
+    /// blank cells are skipped
+    fn points_from(&self, head_y: i32) -> Vec<(usize, u16, u16, char)> {
+        let mut points = vec![];
+        for (index, character) in self.body.iter().enumerate() {
+            let index = index + self.trimmed;
+            let yy = head_y - index as i32;
+            if yy < 0 {
+                break;
+            };
+            if *character != BLANK {
+                points.push((index, self.fx, yy as u16, *character));
+            }
+        }
+        points
+    }
+
+    /// Receive visible body cells with their shading
+    pub fn to_points_styled(
+        &self,
+        options: &DigitalRainOptions,
+    ) -> Vec<StyledPoint> {
+        let length = self.body.len() + self.trimmed;
+        let head_y = self.drawn_head_row(options);
+        let spacing = options.char_spacing.max(1) as usize;
+        let fade_span = length.saturating_sub(1).max(1) as f32;
+        // tail goes past the top edge, body dissolves into it
+        let top_clipped = options.top_fade && head_y < length as i32 - 1;
+        let mut points: Vec<StyledPoint> = self
+            .points_from(head_y)
+            .into_iter()
+            .filter(|(index, ..)| index.is_multiple_of(spacing))
+            .map(|(index, x, y, character)| {
+                let (shade, mut intensity) = self.fade(options, index);
+                if top_clipped && index > 0 && y < TOP_FADE_ROWS {
+                    intensity *= (y + 1) as f32 / (TOP_FADE_ROWS + 1) as f32;
+                }
+                let character =
+                    self.light_char(options, index, fade_span, character);
+                StyledPoint {
+                    x,
+                    y,
+                    character,
+                    index,
+                    shade,
+                    intensity,
+                    hue: (options.hue_shift * index as f32).clamp(-180.0, 180.0),
+                }
+            })
+            .collect();
+
+        // bodies are still simulated, only heads are drawn
+        if options.heads_only {
+            points.retain(|point| point.index == 0);
+            return points;
+        }
+
+        // dim copy of the head right behind it, replacing body cell there
+        if self.has_ghost(options)
+            && let Some(head) = points.first().copied()
+            && head.y > 0
+        {
+            points.retain(|point| point.y != head.y - 1);
+            let ghost = StyledPoint {
+                y: head.y - 1,
+                index: 1,
+                intensity: GHOST_INTENSITY * self.fade_level(options),
+                ..head
+            };
+            points.insert(1, ghost);
+        }
+
+        // split head between its row and the next one by fractional position
+        let fract = self.fy.fract();
+        if options.antialias_head
+            && fract > 0.0
+            && self.trimmed == 0
+            && let Some(head) = points.first_mut()
+        {
+            head.intensity = (1.0 - fract) * self.head_level(options);
+            let lead = StyledPoint {
+                y: head.y + 1,
+                intensity: fract * self.head_level(options),
+                ..*head
+            };
+            points.insert(0, lead);
+        }
+        points
+    }
+
+    /// Gradient position and brightness of body cell at `index`,
+    /// brightness fades linearly from the bright end down to the tail floor
+    fn fade(&self, options: &DigitalRainOptions, index: usize) -> (usize, f32) {
+        let length = self.body.len() + self.trimmed;
+        let shade = match options.tail_direction {
+            TailDirection::Behind => index,
+            TailDirection::Ahead => length - 1 - index,
+        };
+        let fade_span = length.saturating_sub(1).max(1) as f32;
+        let floor = self.tail_floor(options);
+        let intensity = match self.style {
+            RainDropStyle::Solid => 1.0,
+            _ => 1.0 - (1.0 - floor) * shade as f32 / fade_span,
+        };
+        let level = match index {
+            0 => self.head_level(options),
+            _ => self.fade_level(options),
+        };
+        (shade, intensity * level)
+    }
+
+    /// Body cells as index from the head, brightness and glyph,
+    /// for renderers computing colors on their own. Blank cells are skipped
+    pub fn body_iter<'a>(
+        &'a self,
+        options: &'a DigitalRainOptions,
+    ) -> impl Iterator<Item = (usize, f32, char)> + 'a {
+        self.body
+            .iter()
+            .enumerate()
+            .filter(|(_, character)| **character != BLANK)
+            .map(move |(index, character)| {
+                let index = index + self.trimmed;
+                (index, self.fade(options, index).1, *character)
+            })
+    }
+
+    /// Swap body character for a light one, chance grows towards the tail
+    /// and choice is fixed per cell so glyphs do not flicker
+    fn light_char(
+        &self,
+        options: &DigitalRainOptions,
+        index: usize,
+        fade_span: f32,
+        character: char,
+    ) -> char {
+        let Some(light) = options.tail_light_chars.as_ref() else {
+            return character;
+        };
+        let depth = index as f32 / fade_span;
+        if light.is_empty() || depth <= LIGHT_TAIL_START {
+            return character;
+        }
+        let hash = (self.id as u64 ^ (index as u64).rotate_left(32))
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            >> 32;
+        let roll = (hash & 0xffff) as f32 / 0xffff as f32;
+        match roll <= (depth - LIGHT_TAIL_START) / (1.0 - LIGHT_TAIL_START) {
+            true => light[(hash >> 16) as usize % light.len()],
+            false => character,
+        }
+    }
+
+    /// Row the head is drawn on, antialiased head starts from the row above
+    fn drawn_head_row(&self, options: &DigitalRainOptions) -> i32 {
+        match options.antialias_head {
+            true => self.head_row(Rounding::Floor),
+            false => self.head_row(options.cell_rounding),
+        }
+    }
+
+    /// Whether any body cell is drawn inside the screen
+    pub fn is_visible(
+        &self,
+        options: &DigitalRainOptions,
+        screen_size: (u16, u16),
+    ) -> bool {
+        let Ok(head_y) = usize::try_from(self.drawn_head_row(options)) else {
+            return false;
+        };
+        // ghost is drawn right behind the head even for single cell drops
+        let ghost = usize::from(self.has_ghost(options));
+        let length = (self.body.len() + self.trimmed).max(1 + ghost);
+        self.fx < screen_size.0
+            && !self.body.is_empty()
+            && head_y >= self.trimmed
+            && head_y + 1 < length + screen_size.1 as usize
+    }
+
+    /// Number of cells `to_points_styled` yields inside the screen,
+    /// counted without collecting them
+    pub fn visible_cell_count(
+        &self,
+        options: &DigitalRainOptions,
+        screen_size: (u16, u16),
+    ) -> usize {
+        if !self.is_visible(options, screen_size) {
+            return 0;
+        }
+        let height = screen_size.1;
+        let head_y = self.drawn_head_row(options) as usize;
+        if options.heads_only {
+            return (self.trimmed == 0 && head_y < height as usize) as usize;
+        }
+        // body indexes drawn on rows 0..height
+        let first = self
+            .trimmed
+            .max((head_y + 1).saturating_sub(height as usize));
+        let last = (self.trimmed + self.body.len() - 1).min(head_y);
+        if first > last {
+            return 0;
+        }
+        let spacing = options.char_spacing.max(1) as usize;
+        let mut count = (first.div_ceil(spacing) * spacing..=last)
+            .step_by(spacing)
+            .filter(|index| self.body[index - self.trimmed] != BLANK)
+            .count();
+        // ghost takes place of the second body cell or adds a new one
+        let ghost_replaces =
+            spacing == 1 && self.body.get(1).is_some_and(|c| *c != BLANK);
+        if self.has_ghost(options)
+            && (1..=height as usize).contains(&head_y)
+            && !ghost_replaces
+        {
+            count += 1;
+        }
+        if options.antialias_head
+            && self.fy.fract() > 0.0
+            && self.trimmed == 0
+            && head_y + 1 < height as usize
+        {
+            count += 1;
+        }
+        count
+    }
+
+    /// Brightness of the dimmest end of the drop
+    #[inline]
+    pub fn tail_floor(&self, options: &DigitalRainOptions) -> f32 {
+        (options.tail_floor + self.floor_jitter).clamp(0.0, 1.0)
+    }
+
+    /// Pick own shift of the tail floor
+    fn roll_floor_jitter<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
+        let jitter = options.tail_floor_jitter.abs();
+        self.floor_jitter = match jitter > 0.0 {
+            true => rng.random_range(-jitter..=jitter),
+            false => 0.0,
+        };
+    }
+
+    /// Pick time the drop waits before falling
+    fn roll_start_delay<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
+        let (min, max) = options.start_delay_range;
+        let (min, max) = (min.min(max), min.max(max));
+        if !max.is_zero() {
+            let micros = rng.random_range(min.as_micros()..=max.as_micros());
+            self.start_delay = Duration::from_micros(micros as u64);
+        }
+    }
+
+    /// Pick own flicker cadence of the drop
+    fn roll_flicker_interval<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
+        self.flicker_elapsed = Duration::ZERO;
+        self.flicker_interval = match options.flicker_interval_range {
+            Some((min, max)) if !min.max(max).is_zero() => {
+                let (min, max) = (min.min(max), min.max(max));
+                let micros = rng.random_range(min.as_micros()..=max.as_micros());
+                Duration::from_micros(micros as u64)
+            }
+            _ => Duration::ZERO,
+        };
+    }
+
+    /// Time between character swaps of the drop, zero if it never flickers
+    #[inline]
+    pub fn flicker_interval(&self) -> Duration {
+        self.flicker_interval
+    }
+
+    /// Swap one random body character each time flicker interval passes
+    fn flicker<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        dt: Duration,
+        source: Option<&SharedCharSource>,
+        rng: &mut R,
+    ) {
+        if self.flicker_interval.is_zero() {
+            return;
+        }
+        let elapsed = self.flicker_elapsed + dt.min(options.max_step);
+        let interval = self.flicker_interval.as_nanos();
+        self.flicker_elapsed =
+            Duration::from_nanos((elapsed.as_nanos() % interval) as u64);
+        // rare head keeps its glyph
+        let first = usize::from(self.rare_head);
+        if first >= self.body.len() {
+            return;
+        }
+        // swapping more characters than the body has changes nothing
+        let swaps = (elapsed.as_nanos() / interval).min(self.body.len() as u128);
+        for _ in 0..swaps {
+            let index = rng.random_range(first..self.body.len());
+            if self.body[index] != BLANK {
+                let row = self.head_row(options.cell_rounding)
+                    - (index + self.trimmed) as i32;
+                self.body[index] = self.random_char_at(options, row, source, rng);
+            }
+        }
+    }
+
+    /// Make the drop ephemeral with chance set by options
+    fn roll_lifespan<R: Rng + ?Sized>(
+        &mut self,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
+        self.age = Duration::ZERO;
+        self.lifespan = None;
+        let rate = options.ephemeral_rate.clamp(0.0, 1.0) as f64;
+        if rate > 0.0 && rng.random_bool(rate) {
+            let (min, max) = EPHEMERAL_LIFESPAN;
+            let micros = rng.random_range(min.as_micros()..=max.as_micros());
+            self.lifespan = Some(Duration::from_micros(micros as u64));
+        }
+    }
+
+    /// Brightness of the whole drop, ramps up from 0.0 to 1.0 during
+    /// `spawn_fade` and goes back down while ephemeral drop fades out
+    /// at the end of its life
+    pub fn fade_level(&self, options: &DigitalRainOptions) -> f32 {
+        let fade_in = match options.spawn_fade.is_zero() {
+            true => 1.0,
+            false => {
+                (self.age.as_secs_f32() / options.spawn_fade.as_secs_f32()).min(1.0)
+            }
+        };
+        let fade_out = match self.lifespan {
+            Some(lifespan) if self.age > lifespan => {
+                let faded = (self.age - lifespan).as_secs_f32();
+                (1.0 - faded / EPHEMERAL_FADE.as_secs_f32()).max(0.0)
+            }
+            _ => 1.0,
+        };
+        fade_in * fade_out
+    }
+
+    /// Brightness of the head, with `speed_brightness` slower drops
+    /// get dimmer heads
+    fn head_level(&self, options: &DigitalRainOptions) -> f32 {
+        let level = self.fade_level(options);
+        match options.speed_brightness {
+            true => {
+                let max_speed = options.get_max_speed().max(1) as f32;
+                level * (self.speed as f32 / max_speed).clamp(0.0, 1.0)
+            }
+            false => level,
+        }
+    }
+
+    /// Whether head character was inserted during the latest tick
+    #[inline]
+    pub fn is_head_new(&self) -> bool {
+        self.head_age == 0
+    }
+
+    /// Whether the drop still waits for its start delay
+    #[inline]
+    pub fn is_waiting(&self) -> bool {
+        !self.start_delay.is_zero()
+    }
+
+    /// Whether ghost head is drawn behind the head
+    #[inline]
+    fn has_ghost(&self, options: &DigitalRainOptions) -> bool {
+        options.ghost_head
+            && sim::grows_continuously(self.speed)
+            && self.trimmed == 0
+    }
+
+    /// Number of rows passed during `dt`
+    #[inline]
+    pub fn vertical_advance(&self, dt: Duration) -> f32 {
+        sim::advance_rows(self.speed, dt.as_micros() as u64)
+    }
+
+    /// Speed multiplier slowing drop down as its head goes through
+    /// the ease-out zone at the screen bottom
+    fn ease_out(&self, options: &DigitalRainOptions, height: u16) -> f32 {
+        let zone = options.ease_out_zone.clamp(0.0, 1.0) * height as f32;
+        if zone <= 0.0 {
+            return 1.0;
+        }
+        ((height as f32 - self.fy) / zone).clamp(EASE_OUT_MIN, 1.0)
+    }
+
+    /// Remove cells which are below the screen bottom, they are never drawn.
+    /// Should be called only when drop is exiting the screen
+    pub fn trim_offscreen(&mut self, height: u16, rounding: Rounding) {
+        let head_y = rounding.apply(self.fy) as i32;
+        let offscreen = sim::offscreen_cells(head_y, height, self.trimmed);
+        let remove = offscreen.min(self.body.len());
+        self.body.drain(..remove);
+        self.trimmed += remove;
+    }
+
+    /// Reset worm to the sane defaults
+    fn reset<R: Rng + ?Sized>(
+        &mut self,
+        screen_size: (u16, u16),
+        options: &DigitalRainOptions,
+        source: Option<&SharedCharSource>,
+        rng: &mut R,
+    ) {
+        self.body.clear();
+        self.trimmed = 0;
+        self.pending_growth = 0;
+        self.charset = Self::pick_charset(options, rng);
+        self.style = RainDropStyle::random_allowed(&options.allowed_styles, rng);
+        self.pick_glyphs(options, rng);
+        let row = self.head_row(options.cell_rounding);
+        let head = self.random_char_at(options, row, source, rng);
+        self.body.insert(0, head);
+        // staggered drops queue up above the screen instead of all
+        // entering on the same row
+        let queue = match options.stagger {
+            true => sim::stagger_row(self.id, sim::start_band(screen_size.1)),
+            false => 0,
+        };
+        self.fy = -(options.preroll_rows as f32) - queue as f32;
+        self.set_column(rng.random_range(0..screen_size.0));
+        self.speed =
+            rng.random_range(options.get_min_speed()..=options.get_max_speed());
+        self.max_length = match options.length_range {
+            Some(range) => sim::pick_length(range, rng),
+            None => sim::reset_length(screen_size.1, rng),
+        };
+        if options.instant_respawn {
+            let length = RESPAWN_LENGTH
+                .min(self.max_length)
+                .min(screen_size.1.into());
+            while self.body.len() < length {
+                let row = self.head_row(options.cell_rounding);
+                let c = self.random_char_at(options, row, source, rng);
+                self.body.insert(0, c);
+            }
+            // whole body fits right below the top edge
+            self.fy = self.body.len().saturating_sub(1) as f32;
+        }
+        self.roll_rare_head(options, rng);
+        self.roll_floor_jitter(options, rng);
+        self.roll_flicker_interval(options, rng);
+        self.roll_lifespan(options, rng);
+    }
+
+    /// Grow up matrix worm characters array, at most `budget` characters
+    /// are inserted and budget is reduced by their number
+    fn grow<R: Rng + ?Sized>(
+        &mut self,
+        head_y: i32,
+        options: &DigitalRainOptions,
+        budget: &mut usize,
+        source: Option<&SharedCharSource>,
+        rng: &mut R,
+    ) {
+        if self.body.len() >= self.max_length {
+            self.body.truncate(self.max_length);
+            self.pending_growth = 0;
+            return;
+        };
+
+        // rare head travels with the drop, new characters go right behind it
+        let at = usize::from(self.rare_head);
+        let prev_head_y = self.head_row(options.cell_rounding);
+        let delta = (head_y - prev_head_y).max(0) as usize;
+        // characters which would be pushed out right away are never made
+        let room = self.max_length - at;
+        // fast drops grow by the number of cells passed during update,
+        // growth over the budget is deferred to next updates
+        let (inserts, pending) =
+            sim::growth(self.speed, self.pending_growth, delta, room, *budget);
+        self.pending_growth = pending;
+        // tail cells pushed over max length are cut before inserting
+        self.body.truncate((self.max_length - inserts).max(at));
+        let fill = options.body_fill.clamp(0.0, 1.0) as f64;
+        for insert in 0..inserts {
+            // cell behind the new one becomes interior and may be left empty
+            if fill < 1.0
+                && let Some(cell) = self.body.get_mut(at)
+                && !rng.random_bool(fill)
+            {
+                *cell = BLANK;
+            }
+            // the last inserted character ends up on the head row
+            let row = head_y - (inserts - 1 - insert) as i32;
+            let character = self.random_char_at(options, row, source, rng);
+            self.body.insert(at, character);
+        }
+        if inserts > 0 {
+            self.head_age = 0;
+        }
+        *budget -= inserts;
+    }
+
+    /// Update rain drops to change position/grow etc
+    /// there can be 4 cases:
+    /// rain drop vector not yet fully come from top
+    /// rain drop vector somewhere in the middle of the scren
+    /// rain drop vector reach bottom and need to fade out
+    /// raid drop vector tail out of screen rect visibility
+    ///
+    /// Note that rain drop coordiantes can be outside bounds defined
+    /// by screen width and height, this should be handled during draw process
+    ///
+    /// Returns `true` if the drop was reset to the top of the screen
+    pub fn update<R: Rng + ?Sized>(
+        &mut self,
+        screen_size: (u16, u16),
+        options: &DigitalRainOptions,
+        dt: Duration,
+        rng: &mut R,
+    ) -> bool {
+        let mut budget = usize::MAX;
+        self.update_with_budget(screen_size, options, dt, &mut budget, rng)
+    }
+
+    /// Same as `update` but inserts at most `budget` characters,
+    /// budget is reduced by the number of inserted ones
+    pub fn update_with_budget<R: Rng + ?Sized>(
+        &mut self,
+        screen_size: (u16, u16),
+        options: &DigitalRainOptions,
+        dt: Duration,
+        budget: &mut usize,
+        rng: &mut R,
+    ) -> bool {
+        let source = options.char_source.as_ref();
+        self.advance(screen_size, options, dt, budget, source, rng)
+    }
+
+    /// Same as `update` but new characters are taken from `source`
+    /// instead of `options.char_source`, handy to get exact bodies in tests
+    pub fn update_with<R: Rng + ?Sized>(
+        &mut self,
+        screen_size: (u16, u16),
+        options: &DigitalRainOptions,
+        dt: Duration,
+        rng: &mut R,
+        source: &SharedCharSource,
+    ) -> bool {
+        let mut budget = usize::MAX;
+        self.advance(screen_size, options, dt, &mut budget, Some(source), rng)
+    }
+
+    /// Move drop by `dt` growing it on the way
+    fn advance<R: Rng + ?Sized>(
+        &mut self,
+        screen_size: (u16, u16),
+        options: &DigitalRainOptions,
+        mut dt: Duration,
+        budget: &mut usize,
+        source: Option<&SharedCharSource>,
+        rng: &mut R,
+    ) -> bool {
+        // NOTE: looks like guard, but why i even need it here?
+        if self.body.is_empty() {
+            self.reset(screen_size, options, source, rng);
+            return true;
+        }
+
+        // drop went far below the screen (e.g. after resize), coordinates
+        // are kept bounded by resetting it
+        let length = self.max_length.max(self.body.len() + self.trimmed);
+        let limit = screen_size.1 as usize + length;
+        if self.fy.is_nan() || self.fy > limit as f32 {
+            self.reset(screen_size, options, source, rng);
+            return true;
+        }
+
+        self.head_age = self.head_age.saturating_add(1);
+
+        // drop stays in place until start delay passes
+        if self.is_waiting() {
+            let waited = self.start_delay.min(dt);
+            self.start_delay -= waited;
+            dt -= waited;
+            if self.is_waiting() {
+                return false;
+            }
+        }
+
+        self.flicker(options, dt, source, rng);
+
+        // ephemeral drop starts over once it faded out completely
+        if let Some(lifespan) = self.lifespan
+            && self.age >= lifespan + EPHEMERAL_FADE
+        {
+            self.reset(screen_size, options, source, rng);
+            return true;
+        }
+        self.age += dt;
+
+        // new fy coordinate
+        let fy = self.fy
+            + self.vertical_advance(dt.min(options.max_step))
+                * self.ease_out(options, screen_size.1);
+        let head_y = options.cell_rounding.apply(fy) as i32;
+
+        // fast path for the most common case, whole drop is on the screen
+        if head_y <= screen_size.1 as i32
+            && head_y > (self.body.len() + self.trimmed) as i32
+        {
+            self.grow(head_y, options, budget, source, rng);
+            self.fy = fy;
+            self.wiggle(options, screen_size.0);
+            return false;
+        }
+        self.update_by_phase(screen_size, options, fy, budget, source, rng)
+    }
+
+    /// General update path for drop moved to `fy`, handles every phase
+    fn update_by_phase<R: Rng + ?Sized>(
+        &mut self,
+        screen_size: (u16, u16),
+        options: &DigitalRainOptions,
+        fy: f32,
+        budget: &mut usize,
+        source: Option<&SharedCharSource>,
+        rng: &mut R,
+    ) -> bool {
+        // calculate head and tail y coordinate
+        let row = options.cell_rounding.apply(fy);
+        let tail_y = row as i16 - (self.body.len() + self.trimmed) as i16;
+        let height = screen_size.1;
+
+        // drop above the screen is entering, its head row is taken as 0
+        match sim::drop_phase(row as u16, tail_y, height) {
+            DropPhase::Entering | DropPhase::Falling => {
+                self.grow(row as i32, options, budget, source, rng);
+                self.fy = fy;
+                self.wiggle(options, screen_size.0);
+                false
+            }
+            DropPhase::Exiting => {
+                self.fy = fy;
+                self.trim_offscreen(height, options.cell_rounding);
+                self.wiggle(options, screen_size.0);
+                false
+            }
+            DropPhase::Finished => {
+                self.reset(screen_size, options, source, rng);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::digital_rain::{
+            DigitalRainOptionsBuilder, HorizontalEdge, WiggleOptions,
+        },
+        *,
+    };
+
+    fn get_sane_options() -> DigitalRainOptions {
+        DigitalRainOptionsBuilder::default()
+            .drops_range((20, 30))
+            .speed_range((10, 20))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn create_new_and_reset() {
+        let mut rng = rand::rng();
+        let mut new_drop =
+            RainDrop::new((100, 100), &get_sane_options(), 1, &mut rng);
+        assert!(!new_drop.body.is_empty());
+        assert!(new_drop.speed > 0);
+
+        new_drop.reset((100, 100), &get_sane_options(), None, &mut rng);
+        assert_eq!(new_drop.fy, 0.0);
+        assert_eq!(new_drop.id(), 1);
+        assert_eq!(new_drop.body.len(), 1);
+    }
+
+    #[test]
+    fn random_style_hits_all_variants() {
+        let mut rng = rand::rng();
+        let mut seen = vec![];
+        for _ in 0..10_000 {
+            let style = RainDropStyle::random(&mut rng);
+            if !seen.contains(&style) {
+                seen.push(style);
+            }
+        }
+        assert_eq!(seen.len(), RainDropStyle::ALL.len());
+
+        for _ in 0..100 {
+            assert_eq!(
+                RainDropStyle::random_weighted(&[0, 0, 1, 0, 0, 0], &mut rng),
+                RainDropStyle::Back
+            );
+        }
+    }
+
+    #[test]
+    fn repeat_head_init_body() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.init_body_mode = InitBody::RepeatHead;
+        for index in 1..=100 {
+            let new_drop = RainDrop::new((100, 100), &options, index, &mut rng);
+            assert!(new_drop.body.iter().all(|c| *c == new_drop.body[0]));
+        }
+    }
+
+    /// Rng counting how many times it was asked for random data
+    struct CountingRng<R> {
+        rng: R,
+        calls: usize,
+    }
+
+    impl<R: rand::RngCore> rand::RngCore for CountingRng<R> {
+        fn next_u32(&mut self) -> u32 {
+            self.calls += 1;
+            self.rng.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.calls += 1;
+            self.rng.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dst: &mut [u8]) {
+            self.calls += 1;
+            self.rng.fill_bytes(dst)
+        }
+    }
+
+    #[test]
+    fn batched_characters() {
+        let characters = default_characters();
+        let draws = characters.len() * 1000;
+        let mut rng = CountingRng {
+            rng: rand::rng(),
+            calls: 0,
+        };
+        let mut new_drop =
+            RainDrop::from_values(1, vec![], RainDropStyle::Front, 10, 0.0, 5, 10);
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for _ in 0..draws {
+            *counts
+                .entry(new_drop.random_char(&get_sane_options(), &mut rng))
+                .or_default() += 1;
+        }
+        let batched_calls = rng.calls;
+
+        rng.calls = 0;
+        for _ in 0..draws {
+            characters.choose(&mut rng).unwrap();
+        }
+        assert!(batched_calls * 10 < rng.calls);
+
+        // every character is still picked about equally often
+        for character in characters.iter() {
+            let expected =
+                1000 * characters.iter().filter(|c| *c == character).count();
+            let count = counts[character];
+            assert!(count > expected * 8 / 10 && count < expected * 12 / 10);
+        }
+    }
+
+    #[test]
+    fn id_kept_across_resets() {
+        let mut rng = rand::rng();
+        let mut new_drop =
+            RainDrop::new((100, 20), &get_sane_options(), 7, &mut rng);
+        let mut resets = 0;
+        while resets < 3 {
+            if new_drop.update(
+                (100, 20),
+                &get_sane_options(),
+                Duration::from_millis(500),
+                &mut rng,
+            ) {
+                resets += 1;
+            }
+            assert_eq!(new_drop.id(), 7);
+        }
+    }
+
+    #[test]
+    fn per_drop_charsets() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        let sets = vec![vec!['0', '1'], vec!['a', 'b', 'c']];
+        options.per_drop_charsets = sets.clone();
+        let mut used = [false, false];
+        for index in 1..=100 {
+            let mut new_drop = RainDrop::new((100, 100), &options, index, &mut rng);
+            for _ in 0..20 {
+                new_drop.update(
+                    (100, 100),
+                    &options,
+                    Duration::from_millis(100),
+                    &mut rng,
+                );
+            }
+            let charset = new_drop.charset.unwrap();
+            used[charset] = true;
+            assert!(new_drop.body.iter().all(|c| sets[charset].contains(c)));
+        }
+        assert_eq!(used, [true, true]);
+    }
+
+    #[test]
+    fn generate_a_lot_of_drops() {
+        let mut rng = rand::rng();
+        let mut drops = vec![];
+        for index in 1..=1000 {
+            drops.push(RainDrop::new(
+                (100, 100),
+                &get_sane_options(),
+                index,
+                &mut rng,
+            ));
+        }
+        assert_eq!(drops.len(), 1000);
+    }
+
+    #[test]
+    fn to_point() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Gradient,
+            10,
+            10.8,
+            20,
+            10,
+        );
+        let (x, y) = new_drop.to_point();
+        assert_eq!(x, 10);
+        assert_eq!(y, 11);
+    }
+
+    #[test]
+    fn cell_rounding_maps_rows() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Gradient,
+            10,
+            10.8,
+            20,
+            10,
+        );
+        assert_eq!(new_drop.to_point_with(Rounding::Floor), (10, 10));
+        assert_eq!(new_drop.to_point_with(Rounding::Ceil), (10, 11));
+
+        let mut options = get_sane_options();
+        options.cell_rounding = Rounding::Floor;
+        assert_eq!(new_drop.to_points_styled(&options)[0].y, 10);
+        assert_eq!(new_drop.to_points(&options)[0].1, 10);
+        assert_eq!(new_drop.to_points_vec_with(Rounding::Floor)[0].1, 10);
+    }
+
+    #[test]
+    fn solid_drop_has_flat_intensity() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c', 'd', 'e'],
+            RainDropStyle::Solid,
+            10,
+            10.0,
+            10,
+            8,
+        );
+        let mut options = get_sane_options();
+        options.tail_floor = 0.1;
+        let points = new_drop.to_points_styled(&options);
+        assert_eq!(points.len(), 5);
+        for point in points.iter() {
+            assert_eq!(point.intensity, points[0].intensity);
+        }
+        assert_eq!(points[0].intensity, 1.0);
+    }
+
+    #[test]
+    fn top_fade_dissolves_clipped_tail() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a'; 8],
+            RainDropStyle::Solid,
+            10,
+            4.0,
+            10,
+            8,
+        );
+        let mut options = get_sane_options();
+        let intensity_at = |options: &DigitalRainOptions, y: u16| {
+            let points = new_drop.to_points_styled(options);
+            points.iter().find(|point| point.y == y).unwrap().intensity
+        };
+        assert_eq!(intensity_at(&options, 0), intensity_at(&options, 3));
+
+        options.top_fade = true;
+        assert!(intensity_at(&options, 0) < intensity_at(&options, 1));
+        assert!(intensity_at(&options, 1) < intensity_at(&options, 2));
+        assert!(intensity_at(&options, 2) < intensity_at(&options, 3));
+        assert_eq!(intensity_at(&options, 4), 1.0);
+    }
+
+    #[test]
+    fn clone_at_moves_copy() {
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Middle,
+            10,
+            10.0,
+            10,
+            8,
+        );
+        new_drop.pending_growth = 3;
+        new_drop.char_batch.next = 0;
+        let copy = new_drop.clone_at(20, 3.5, 7);
+        assert_eq!(copy.id(), 7);
+        assert_eq!((copy.fx, copy.base_fx, copy.fy), (20, 20, 3.5));
+        assert_eq!(copy.body, new_drop.body);
+        assert_eq!(copy.style, new_drop.style);
+        assert_eq!(copy.speed, new_drop.speed);
+        assert_eq!(copy.pending_growth, 0);
+        assert_eq!(copy.char_batch.next, CHAR_BATCH_SIZE);
+        assert_eq!((new_drop.id(), new_drop.fx, new_drop.fy), (1, 10, 10.0));
+    }
+
+    #[test]
+    fn to_point_vec() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Fading,
+            10,
+            10.0,
+            10,
+            8,
+        );
+        let points = new_drop.to_points_vec();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0], (10, 10, 'a'));
+    }
+
+    #[test]
+    fn tail_direction_moves_bright_end() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Front,
+            10,
+            10.0,
+            10,
+            8,
+        );
+        let brightest = |options: &DigitalRainOptions| {
+            let points = new_drop.to_points_styled(options);
+            points.into_iter().min_by_key(|p| p.shade).map(|p| p.y)
+        };
+
+        let mut options = get_sane_options();
+        // falling down, so leading edge is the lowest cell
+        assert_eq!(brightest(&options), Some(10));
+        options.tail_direction = TailDirection::Ahead;
+        assert_eq!(brightest(&options), Some(8));
+    }
+
+    #[test]
+    fn antialias_head_split() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b'],
+            RainDropStyle::Front,
+            10,
+            10.5,
+            10,
+            8,
+        );
+        let mut options = get_sane_options();
+        let points = new_drop.to_points_styled(&options);
+        assert_eq!(points.len(), 2);
+        assert!(points.iter().all(|p| p.intensity == 1.0));
+
+        options.antialias_head = true;
+        let points = new_drop.to_points_styled(&options);
+        assert_eq!(points.len(), 3);
+        assert_eq!((points[0].y, points[0].character), (11, 'a'));
+        assert_eq!((points[1].y, points[1].character), (10, 'a'));
+        assert_eq!((points[2].y, points[2].character), (9, 'b'));
+        assert!((points[0].intensity - 0.5).abs() < 0.01);
+        assert!((points[1].intensity - 0.5).abs() < 0.01);
+        assert_eq!(points[2].intensity, 1.0);
+    }
+
+    #[test]
+    fn char_spacing_leaves_gaps() {
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c', 'd', 'e'],
+            RainDropStyle::Front,
+            10,
+            10.0,
+            10,
+            8,
+        );
+        let mut options = get_sane_options();
+        options.char_spacing = 2;
+        let rows: Vec<u16> = new_drop
+            .to_points_styled(&options)
+            .iter()
+            .map(|p| p.y)
+            .collect();
+        assert_eq!(rows, vec![10, 8, 6]);
+        let rows: Vec<u16> =
+            new_drop.to_points(&options).iter().map(|p| p.1).collect();
+        assert_eq!(rows, vec![10, 8, 6]);
+    }
+
+    #[test]
+    fn is_visible_matches_drawn_cells() {
+        let mut options = get_sane_options();
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Front,
+            10,
+            -1.0,
+            10,
+            10,
+        );
+        let visible = |new_drop: &RainDrop, options: &DigitalRainOptions| {
+            let drawn = new_drop
+                .to_points_styled(options)
+                .iter()
+                .any(|p| p.x < 20 && p.y < 20);
+            assert_eq!(new_drop.is_visible(options, (20, 20)), drawn);
+            drawn
+        };
+        assert!(!visible(&new_drop, &options));
+        for fy in [0.0, 10.0, 21.0] {
+            new_drop.fy = fy;
+            assert!(visible(&new_drop, &options));
+        }
+        new_drop.fy = 22.0;
+        assert!(!visible(&new_drop, &options));
+
+        // ghost of single cell drop shows up above the bottom edge
+        options.ghost_head = true;
+        new_drop.body.truncate(1);
+        new_drop.fy = 20.0;
+        assert!(visible(&new_drop, &options));
+    }
+
+    #[test]
+    fn instant_respawn_is_visible() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.instant_respawn = true;
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'; 3],
+            RainDropStyle::Front,
+            10,
+            200.0,
+            3,
+            8,
+        );
+        let reset = new_drop.update(
+            (100, 100),
+            &options,
+            Duration::from_millis(100),
+            &mut rng,
+        );
+        assert!(reset);
+        assert!(new_drop.body.len() > 1);
+        let points = new_drop.to_points_vec();
+        assert_eq!(points.len(), new_drop.body.len());
+        assert!(points.iter().all(|(_, y, _)| *y < 100));
+    }
+
+    #[test]
+    fn char_group_from_str() {
+        for (key, group) in [
+            ("digits", CharGroup::Digits),
+            ("punctuation", CharGroup::Punctuation),
+            ("katakana", CharGroup::Katakana),
+            ("other", CharGroup::Other),
+        ] {
+            assert_eq!(key.parse(), Ok(group));
+            assert_eq!(CharGroup::try_from(key), Ok(group));
+            assert!(CHARACTERS_MAP.contains_key(key));
+        }
+        assert_eq!(
+            CharGroup::try_from("kanji"),
+            Err(CharGroupError::Unknown("kanji".to_string()))
+        );
+    }
+
+    #[test]
+    fn mid_screen_fast_path_matches_general() {
+        let mut rng = rand::rng();
+        let mut unlimited = usize::MAX;
+        let mut options = get_sane_options();
+        options.characters = vec!['x'];
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['x'; 5],
+            RainDropStyle::Front,
+            10,
+            20.0,
+            30,
+            12,
+        );
+        let mut fast = new_drop.clone();
+        let mut general = new_drop;
+        let dt = Duration::from_millis(50);
+        for _ in 0..100 {
+            fast.update((100, 100), &options, dt, &mut rng);
+            let fy = general.fy + general.vertical_advance(dt);
+            general.update_by_phase(
+                (100, 100),
+                &options,
+                fy,
+                &mut unlimited,
+                None,
+                &mut rng,
+            );
+            assert_eq!(fast.fy, general.fy);
+            assert_eq!(fast.body, general.body);
+            assert_eq!(fast.trimmed, general.trimmed);
+        }
+    }
+
+    #[test]
+    fn rare_head_always_picked() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.rare_head = Some((1.0, vec!['@', '#']));
+        options.characters = vec!['x'];
+        for index in 1..=50 {
+            let mut new_drop = RainDrop::new((100, 100), &options, index, &mut rng);
+            assert!(['@', '#'].contains(&new_drop.body[0]));
+            assert!(new_drop.body[1..].iter().all(|c| *c == 'x'));
+            // head keeps its glyph while drop grows
+            new_drop.speed = 20;
+            new_drop.max_length = 100;
+            new_drop.fy = 10.0;
+            let head = new_drop.body[0];
+            new_drop.update((100, 100), &options, Duration::from_secs(1), &mut rng);
+            assert_eq!(new_drop.body[0], head);
+            assert!(new_drop.body[1..].iter().all(|c| *c == 'x'));
+        }
+    }
+
+    #[test]
+    fn wiggle_oscillates_around_base() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.wiggle = Some(WiggleOptions {
+            amplitude: 3.0,
+            frequency: 0.5,
+        });
+        options.aspect_ratio = 1.0;
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            50,
+            0.0,
+            10,
+            10,
+        );
+        let mut columns = std::collections::HashSet::new();
+        for _ in 0..100 {
+            new_drop.update(
+                (100, 200),
+                &options,
+                Duration::from_millis(50),
+                &mut rng,
+            );
+            assert_eq!(new_drop.base_fx, 50);
+            assert!(new_drop.fx.abs_diff(50) <= 3);
+            columns.insert(new_drop.to_point().0);
+        }
+        assert!(columns.iter().any(|x| *x < 50));
+        assert!(columns.iter().any(|x| *x > 50));
+    }
+
+    #[test]
+    fn style_name_round_trip() {
+        for style in RainDropStyle::ALL {
+            assert_eq!(style.to_string().parse(), Ok(style));
+        }
+        assert_eq!(RainDropStyle::Fading.to_string(), "fading");
+        assert_eq!(
+            "Front".parse::<RainDropStyle>(),
+            Err(RainDropStyleError::Unknown("Front".to_string()))
+        );
+    }
+
+    #[test]
+    fn only_allowed_styles() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.allowed_styles = vec![RainDropStyle::Front];
+        for index in 1..=100 {
+            let mut new_drop = RainDrop::new((100, 100), &options, index, &mut rng);
+            assert_eq!(new_drop.style, RainDropStyle::Front);
+            new_drop.reset((100, 100), &options, None, &mut rng);
+            assert_eq!(new_drop.style, RainDropStyle::Front);
+        }
+    }
+
+    #[test]
+    fn grow_within_budget() {
+        let mut rng = rand::rng();
+        let mut unlimited = usize::MAX;
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            10,
+            10.0,
+            50,
+            10,
+        );
+        let mut budget = 2;
+        new_drop.grow(15, &get_sane_options(), &mut budget, None, &mut rng);
+        assert_eq!(new_drop.body.len(), 3);
+        assert_eq!(budget, 0);
+
+        // deferred growth catches up once budget is back
+        new_drop.fy = 15.0;
+        new_drop.grow(15, &get_sane_options(), &mut unlimited, None, &mut rng);
+        assert_eq!(new_drop.body.len(), 6);
+    }
+
+    #[test]
+    fn style_charset_pools() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.style_charsets =
+            HashMap::from([(RainDropStyle::Front, vec!['A', 'B', 'C'])]);
+        for index in 1..=100 {
+            let mut new_drop = RainDrop::new((100, 100), &options, index, &mut rng);
+            let front_pool = new_drop.body.iter().all(|c| "ABC".contains(*c));
+            assert_eq!(front_pool, new_drop.style == RainDropStyle::Front);
+
+            new_drop.reset((100, 100), &options, None, &mut rng);
+            let front_pool = new_drop.body.iter().all(|c| "ABC".contains(*c));
+            assert_eq!(front_pool, new_drop.style == RainDropStyle::Front);
+        }
+    }
+
+    struct FixedSource(char);
+
+    impl CharSource for FixedSource {
+        fn next_char(
+            &mut self,
+            _rng: &mut dyn RngCore,
+            _style: RainDropStyle,
+            _index: usize,
+        ) -> char {
+            self.0
+        }
+    }
+
+    #[test]
+    fn custom_char_source() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.init_body_mode = InitBody::Random;
+        options.char_source = Some(SharedCharSource::new(FixedSource('X')));
+        for index in 1..=20 {
+            let mut new_drop = RainDrop::new((100, 100), &options, index, &mut rng);
+            for _ in 0..10 {
+                new_drop.update(
+                    (100, 100),
+                    &options,
+                    Duration::from_millis(100),
+                    &mut rng,
+                );
+                assert!(new_drop.body.iter().all(|c| *c == 'X'));
+            }
+            let source = options.char_source.as_ref();
+            new_drop.reset((100, 100), &options, source, &mut rng);
+            assert!(new_drop.body.iter().all(|c| *c == 'X'));
+        }
+    }
+
+    #[test]
+    fn ghost_head_behind_fast_drop() {
+        let mut options = get_sane_options();
+        options.ghost_head = true;
+        let new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Front,
+            10,
+            10.0,
+            10,
+            12,
+        );
+        let points = new_drop.to_points_styled(&options);
+        assert_eq!(points.len(), 3);
+        let ghost = points.iter().find(|p| p.y == 9).unwrap();
+        assert_eq!(ghost.character, 'a');
+        assert!(ghost.intensity < 1.0);
+
+        // slow drops have no ghost
+        let mut slow_drop = new_drop.clone();
+        slow_drop.speed = 4;
+        let points = slow_drop.to_points_styled(&options);
+        assert_eq!(points.iter().find(|p| p.y == 9).unwrap().character, 'b');
+    }
+
+    #[test]
+    fn preroll_enters_with_body() {
+        let mut rng = rand::rng();
+        let first_visible_length =
+            |preroll_rows: u16, rng: &mut rand::rngs::ThreadRng| {
+                let mut options = get_sane_options();
+                options.preroll_rows = preroll_rows;
+                let mut new_drop = RainDrop::new((100, 100), &options, 1, rng);
+                new_drop.reset((100, 100), &options, None, rng);
+                assert_eq!(new_drop.fy, -(preroll_rows as f32));
+                while new_drop.to_points_vec().is_empty() {
+                    new_drop.update(
+                        (100, 100),
+                        &options,
+                        Duration::from_millis(50),
+                        rng,
+                    );
+                }
+                new_drop.body.len()
+            };
+        assert_eq!(first_visible_length(0, &mut rng), 1);
+        assert!(first_visible_length(5, &mut rng) > 1);
+    }
+
+    #[test]
+    fn light_chars_at_tail_end() {
+        let mut options = get_sane_options();
+        options.tail_light_chars = Some(vec!['.', ',', '\'']);
+        for id in 0..20 {
+            let drop = RainDrop::from_values(
+                id,
+                vec!['a'; 20],
+                RainDropStyle::Back,
+                0,
+                30.0,
+                20,
+                10,
+            );
+            let points = drop.to_points_styled(&options);
+            assert_eq!(points.len(), 20);
+            assert_eq!(points[0].character, 'a');
+            assert!(".,'".contains(points[19].character));
+            assert!(points[..10].iter().all(|p| p.character == 'a'));
+        }
+    }
+
+    #[test]
+    fn start_delay_holds_drop() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut options = get_sane_options();
+        options.start_delay_range =
+            (Duration::from_secs(1), Duration::from_secs(2));
+        let mut new_drop = RainDrop::new((100, 100), &options, 1, &mut rng);
+        let start = new_drop.fy;
+        for _ in 0..19 {
+            new_drop.update(
+                (100, 100),
+                &options,
+                Duration::from_millis(50),
+                &mut rng,
+            );
+            assert_eq!(new_drop.fy, start);
+        }
+        for _ in 0..30 {
+            new_drop.update(
+                (100, 100),
+                &options,
+                Duration::from_millis(50),
+                &mut rng,
+            );
+        }
+        assert!(!new_drop.is_waiting());
+        assert!(new_drop.fy > start);
+    }
+
+    #[test]
+    fn drops_flicker_on_own_schedule() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut options = get_sane_options();
+        options.flicker_interval_range =
+            Some((Duration::from_millis(100), Duration::from_secs(1)));
+        let mut schedules = vec![];
+        for id in 1..=2 {
+            let mut new_drop = RainDrop::new((100, 100), &options, id, &mut rng);
+            // still drop, so only flicker changes the body
+            new_drop.speed = 0;
+            let mut schedule = vec![];
+            for tick in 0..60 {
+                let body = new_drop.body.clone();
+                new_drop.update(
+                    (100, 100),
+                    &options,
+                    Duration::from_millis(50),
+                    &mut rng,
+                );
+                if new_drop.body != body {
+                    schedule.push(tick);
+                }
+            }
+            assert!(!schedule.is_empty());
+            schedules.push((new_drop.flicker_interval(), schedule));
+        }
+        assert_ne!(schedules[0].0, schedules[1].0);
+        assert_ne!(schedules[0].1, schedules[1].1);
+    }
+
+    #[test]
+    fn flicker_after_stall_is_bounded() {
+        let mut rng = rand::rng();
+        let options = get_sane_options();
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'; 5],
+            RainDropStyle::Front,
+            10,
+            10.0,
+            10,
+            0,
+        );
+        new_drop.flicker_interval = Duration::from_nanos(1);
+        new_drop.flicker(&options, Duration::from_secs(3600), None, &mut rng);
+        assert!(new_drop.flicker_elapsed < new_drop.flicker_interval);
+        assert!(new_drop.body.iter().any(|c| *c != 'a'));
+    }
+
+    #[test]
+    fn stagger_spreads_heads() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(3);
+        // widest run of rows without a head within the start band
+        let mut widest_gap = |stagger: bool| {
+            let mut options = get_sane_options();
+            options.stagger = stagger;
+            let mut heads: Vec<u16> = (1..=50)
+                .map(|id| {
+                    RainDrop::new((100, 400), &options, id, &mut rng).fy as u16
+                })
+                .collect();
+            heads.sort_unstable();
+            assert!(heads.iter().all(|y| *y < sim::start_band(400)));
+            heads
+                .windows(2)
+                .map(|w| w[1] - w[0])
+                .chain([heads[0], sim::start_band(400) - heads[heads.len() - 1]])
+                .max()
+                .unwrap()
+        };
+        assert!(widest_gap(true) < widest_gap(false));
+    }
+
+    #[test]
+    fn stagger_queues_reset_drops() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.stagger = true;
+        let rows: Vec<f32> = (1..=8)
+            .map(|id| {
+                let mut drop = RainDrop::new((100, 100), &options, id, &mut rng);
+                drop.reset((100, 100), &options, None, &mut rng);
+                drop.fy
+            })
+            .collect();
+        assert!(rows.iter().all(|y| *y <= -(options.preroll_rows as f32)));
+        assert!(rows.windows(2).any(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn body_iter_fades_along_tail() {
+        let mut options = get_sane_options();
+        options.tail_floor = 0.2;
+        let drop = RainDrop::from_values(
+            1,
+            vec!['a'; 8],
+            RainDropStyle::Back,
+            0,
+            20.0,
+            10,
+            10,
+        );
+        let cells: Vec<(usize, f32, char)> = drop.body_iter(&options).collect();
+        assert_eq!(cells.len(), 8);
+        assert_eq!(cells[0], (0, 1.0, 'a'));
+        assert!(cells.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!((cells[7].1 - 0.2).abs() < 1e-6);
+
+        let points = drop.to_points_styled(&options);
+        for (cell, point) in cells.iter().zip(points.iter()) {
+            assert_eq!((cell.0, cell.1), (point.index, point.intensity));
         }
-
-        Self::from_values(drop_id, body, style, fx, fy, max_length, speed)
     }
 
-    /// Create new worm from values
-    #[inline(always)]
-    pub fn from_values(
-        _drop_id: usize,
-        body: Vec<char>,
-        style: RainDropStyle,
-        fx: u16,
-        fy: f32,
-        max_length: usize,
-        speed: u16,
-    ) -> Self {
-        Self {
-            _drop_id,
-            body,
-            style,
-            fx,
-            fy,
-            max_length,
-            speed,
+    #[test]
+    fn glyphs_per_drop_limits_body() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.glyphs_per_drop = Some(3);
+        options.init_body_mode = InitBody::Random;
+        let distinct = |drop: &RainDrop| {
+            drop.body
+                .iter()
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+        for id in 1..=20 {
+            let mut new_drop = RainDrop::new((100, 100), &options, id, &mut rng);
+            for _ in 0..40 {
+                new_drop.update(
+                    (100, 100),
+                    &options,
+                    Duration::from_millis(100),
+                    &mut rng,
+                );
+                assert!(distinct(&new_drop) <= 3);
+            }
         }
     }
 
-    /// Convert float into screen coordinates
-    #[inline]
-    pub fn to_point(&self) -> (u16, u16) {
-        let x = self.fx;
-        let y = self.fy.round() as u16;
-        (x, y)
-    }
+    #[test]
+    fn filtered_pool_kept_per_drop() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.char_groups = vec![CharGroup::Digits];
+        let mut new_drop = RainDrop::new((100, 100), &options, 1, &mut rng);
+        let digits: Vec<char> = ('0'..='9').collect();
+        assert_eq!(new_drop.glyphs, digits);
+        new_drop.reset((100, 100), &options, None, &mut rng);
+        assert_eq!(new_drop.glyphs, digits);
 
-    /// Receive vector of coordinates of RainDrop body
-    pub fn to_points_vec(&self) -> Vec<(u16, u16, char)> {
-        let mut points = vec![];
-        let (head_x, head_y) = self.to_point();
-        for (index, character) in self.body.iter().enumerate() {
-            let yy = head_y as i16 - index as i16;
-            if yy >= 0 {
-                points.push((head_x, yy as u16, *character));
-            } else {
-                break;
-            };
-        }
-        points
+        // borrowed pools are not copied
+        options.char_groups.clear();
+        new_drop.reset((100, 100), &options, None, &mut rng);
+        assert!(new_drop.glyphs.is_empty());
     }
 
-    /// Reset worm to the sane defaults
-    fn reset(
-        &mut self,
-        screen_size: (u16, u16),
-        options: &DigitalRainOptions,
-        rng: &mut rand::prelude::ThreadRng,
-    ) {
-        self.body.clear();
-        self.body.insert(0, *CHARACTERS.choose(rng).unwrap());
-        self.style = rand::random();
-        self.fy = 0.0;
-        self.fx = rng.random_range(0..screen_size.0);
-        self.speed =
-            rng.random_range(options.get_min_speed()..=options.get_max_speed());
-        self.max_length =
-            rng.random_range(screen_size.1 / 4 + 1..=(screen_size.1 / 2)) as usize;
+    #[test]
+    fn huge_delta_grow_inserts_only_what_fits() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let mut rng = rand::rng();
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Back,
+            0,
+            0.0,
+            10,
+            10,
+        );
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let source = SharedCharSource::new(move || {
+            let calls = counter.fetch_add(1, Ordering::Relaxed) + 1;
+            char::from_digit(calls % 10, 10).unwrap()
+        });
+        let mut unlimited = usize::MAX;
+        new_drop.grow(
+            1000,
+            &get_sane_options(),
+            &mut unlimited,
+            Some(&source),
+            &mut rng,
+        );
+        assert_eq!(calls.load(Ordering::Relaxed), 10);
+        assert_eq!(new_drop.body.len(), 10);
+        assert_eq!(new_drop.body.iter().collect::<String>(), "0987654321");
     }
 
-    /// Grow condition
-    fn grow_condition(&self) -> bool {
-        self.speed > 8
+    #[test]
+    fn spawn_fade_ramps_up_intensity() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.spawn_fade = Duration::from_millis(400);
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'; 4],
+            RainDropStyle::Back,
+            0,
+            20.0,
+            10,
+            1,
+        );
+        let head = |new_drop: &RainDrop| new_drop.to_points_styled(&options)[0];
+        assert_eq!(head(&new_drop).intensity, 0.0);
+        let step = Duration::from_millis(100);
+        new_drop.update((100, 100), &options, step, &mut rng);
+        let early = head(&new_drop).intensity;
+        assert!(early > 0.0 && early < 1.0);
+        for _ in 0..3 {
+            new_drop.update((100, 100), &options, step, &mut rng);
+        }
+        assert_eq!(head(&new_drop).intensity, 1.0);
     }
 
-    /// Grow up matrix worm characters array
-    fn grow(&mut self, head_y: u16, rng: &mut rand::prelude::ThreadRng) {
-        if self.body.len() >= self.max_length {
-            self.body.truncate(self.max_length);
-            return;
-        };
+    #[test]
+    fn ephemeral_drop_fades_out() {
+        use rand::{SeedableRng, rngs::StdRng};
 
-        match self.grow_condition() {
-            true => {
-                // grow drop body to the number of cells passed during update
-                let delta: i16 = head_y as i16 - self.fy.round() as i16;
-                if delta > 0 {
-                    for _ in 0..delta as usize {
-                        self.body.insert(0, *CHARACTERS.choose(rng).unwrap());
-                    }
-                };
-            }
-            false => {
-                // grow only to one character if position changed
-                let delta: i16 = head_y as i16 - self.fy.round() as i16;
-                if delta > 0 {
-                    self.body.insert(0, *CHARACTERS.choose(rng).unwrap());
-                };
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut options = get_sane_options();
+        options.ephemeral_rate = 1.0;
+        let screen_size = (100, 1000);
+        let mut new_drop = RainDrop::new(screen_size, &options, 1, &mut rng);
+        let mut lowest: f32 = 1.0;
+        let mut reset = false;
+        for _ in 0..200 {
+            lowest = lowest.min(new_drop.fade_level(&options));
+            let head = new_drop.to_points_styled(&options)[0];
+            assert!((head.intensity - new_drop.fade_level(&options)).abs() < 1e-6);
+            if new_drop.update(
+                screen_size,
+                &options,
+                Duration::from_millis(50),
+                &mut rng,
+            ) {
+                reset = true;
+                break;
             }
-        };
-
-        self.body.truncate(self.max_length);
+            assert!(new_drop.fy < screen_size.1 as f32);
+        }
+        assert!(reset);
+        assert!(lowest < 0.15);
     }
 
-    /// Update rain drops to change position/grow etc
-    /// there can be 4 cases:
-    /// rain drop vector not yet fully come from top
-    /// rain drop vector somewhere in the middle of the scren
-    /// rain drop vector reach bottom and need to fade out
-    /// raid drop vector tail out of screen rect visibility
-    ///
-    /// Note that rain drop coordiantes can be outside bounds defined
-    /// by screen width and height, this should be handled during draw process
-    pub fn update(
-        &mut self,
-        screen_size: (u16, u16),
-        options: &DigitalRainOptions,
-        dt: Duration,
-        rng: &mut rand::prelude::ThreadRng,
-    ) {
-        // NOTE: looks like guard, but why i even need it here?
-        if self.body.is_empty() {
-            self.reset(screen_size, options, rng);
-            return;
+    #[test]
+    fn short_length_range() {
+        let mut rng = rand::rng();
+        for range in [(1, 1), (2, 2), (1, 3)] {
+            let mut options = get_sane_options();
+            options.length_range = Some(range);
+            for id in 0..20 {
+                let mut new_drop =
+                    RainDrop::new((100, 100), &options, id, &mut rng);
+                assert!(
+                    (range.0..=range.1).contains(&(new_drop.max_length as u16))
+                );
+                assert_eq!(new_drop.body.len(), 1);
+                for _ in 0..50 {
+                    new_drop.update(
+                        (100, 100),
+                        &options,
+                        Duration::from_millis(100),
+                        &mut rng,
+                    );
+                    assert!(new_drop.body.len() <= range.1 as usize);
+                }
+            }
         }
+    }
 
-        // new fy coordinate
-        let fy = self.fy + (self.speed as f32 * dt.as_millis() as f32) / 1000.0;
-
-        // calculate head and tail y coordinate
-        let head_y = fy.round() as u16;
-        let tail_y = fy.round() as i16 - self.body.len() as i16;
-        let height = screen_size.1;
-
-        if tail_y <= 0 {
-            // not fully come out from top
-            self.grow(head_y, rng);
-            self.fy = fy;
-            return;
-        };
-
-        if (head_y <= height) && (tail_y > 0) {
-            // somewhere in the middle
-            self.grow(head_y, rng);
-            self.fy = fy;
-            return;
-        };
-
-        if (head_y > height) && (tail_y < height as i16) {
-            // got to the bottom
-            self.fy = fy;
-            return;
+    #[test]
+    fn speed_brightness_heads() {
+        let mut options = get_sane_options();
+        options.speed_brightness = true;
+        let (min_speed, max_speed) = options.speed_range;
+        let head = |speed: u16| {
+            let drop = RainDrop::from_values(
+                1,
+                vec!['a'; 4],
+                RainDropStyle::Back,
+                0,
+                20.0,
+                10,
+                speed,
+            );
+            drop.to_points_styled(&options)[0].intensity
         };
+        assert_eq!(head(max_speed), 1.0);
+        assert!(head(max_speed) > head(min_speed));
+    }
 
-        // NOTE: need this to reset
-        if tail_y as u16 >= height {
-            self.reset(screen_size, options, rng);
+    #[test]
+    fn ease_out_slows_drops_near_bottom() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.ease_out_zone = 0.25;
+        let advance = |fy: f32, rng: &mut rand::rngs::ThreadRng| {
+            let mut new_drop = RainDrop::from_values(
+                1,
+                vec!['a'; 4],
+                RainDropStyle::Back,
+                0,
+                fy,
+                4,
+                10,
+            );
+            new_drop.update((100, 40), &options, Duration::from_millis(100), rng);
+            new_drop.fy - fy
         };
+        let high = advance(10.0, &mut rng);
+        let low = advance(36.0, &mut rng);
+        assert!(low > 0.0);
+        assert!(low < high);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{super::digital_rain::DigitalRainOptionsBuilder, *};
 
-    fn get_sane_options() -> DigitalRainOptions {
-        DigitalRainOptionsBuilder::default()
-            .drops_range((20, 30))
-            .speed_range((10, 20))
-            .build()
-            .unwrap()
+    #[test]
+    fn long_drop_below_bottom_does_not_reset() {
+        let mut rng = rand::rng();
+        // head is two rows below the bottom, tail still above the top
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'; 15],
+            RainDropStyle::Back,
+            0,
+            12.0,
+            20,
+            10,
+        );
+        let options = get_sane_options();
+        for _ in 0..5 {
+            let reset = new_drop.update(
+                (100, 10),
+                &options,
+                Duration::from_millis(10),
+                &mut rng,
+            );
+            assert!(!reset);
+        }
+        assert!(new_drop.fy > 12.0);
+        assert!(new_drop.body.len() >= 15);
     }
 
     #[test]
-    fn create_new_and_reset() {
+    fn region_charsets_by_row() {
         let mut rng = rand::rng();
-        let mut new_drop =
-            RainDrop::new((100, 100), &get_sane_options(), 1, &mut rng);
-        assert!(!new_drop.body.is_empty());
-        assert!(new_drop.speed > 0);
-
-        new_drop.reset((100, 100), &get_sane_options(), &mut rng);
-        assert_eq!(new_drop.fy, 0.0);
-        assert_eq!(new_drop._drop_id, 1);
-        assert_eq!(new_drop.body.len(), 1);
+        let mut options = get_sane_options();
+        options.region_charsets = vec![(0..10, vec!['A']), (10..100, vec!['b'])];
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['A'],
+            RainDropStyle::Back,
+            0,
+            0.0,
+            100,
+            20,
+        );
+        for _ in 0..20 {
+            new_drop.update(
+                (100, 100),
+                &options,
+                Duration::from_millis(100),
+                &mut rng,
+            );
+        }
+        let points = new_drop.to_points_vec();
+        assert!(points.len() > 30);
+        for (_, y, character) in points {
+            assert_eq!(character, if y < 10 { 'A' } else { 'b' });
+        }
     }
 
     #[test]
-    fn generate_a_lot_of_drops() {
+    fn huge_fy_resets() {
         let mut rng = rand::rng();
-        let mut drops = vec![];
-        for index in 1..=1000 {
-            drops.push(RainDrop::new(
+        for fy in [70_000.0, 1e30, f32::INFINITY, f32::NAN] {
+            let mut new_drop = RainDrop::from_values(
+                1,
+                vec!['a'; 3],
+                RainDropStyle::Front,
+                10,
+                fy,
+                5,
+                10,
+            );
+            let reset = new_drop.update(
                 (100, 100),
                 &get_sane_options(),
-                index,
+                Duration::from_millis(50),
                 &mut rng,
-            ));
+            );
+            assert!(reset);
+            assert!(new_drop.fy < 100.0);
         }
-        assert_eq!(drops.len(), 1000);
     }
 
     #[test]
-    fn to_point() {
-        let new_drop = RainDrop::from_values(
+    fn body_fill_leaves_holes() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.body_fill = 0.5;
+        let mut new_drop = RainDrop::from_values(
             1,
             vec!['a'],
-            RainDropStyle::Gradient,
+            RainDropStyle::Front,
             10,
-            10.8,
+            0.0,
+            1000,
             20,
+        );
+        for _ in 0..40 {
+            new_drop.update(
+                (100, 2000),
+                &options,
+                Duration::from_secs(1),
+                &mut rng,
+            );
+        }
+        let length = new_drop.body.len();
+        let drawn = new_drop.to_points_vec().len();
+        assert!(length > 500);
+        assert_ne!(new_drop.body[0], BLANK);
+        assert!((drawn as f32 / length as f32 - 0.5).abs() < 0.1);
+        assert_eq!(new_drop.to_points_styled(&options).len(), drawn);
+    }
+
+    #[test]
+    fn wiggle_wraps_around_edge() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.wiggle = Some(WiggleOptions {
+            amplitude: 5.0,
+            frequency: 0.5,
+        });
+        options.aspect_ratio = 1.0;
+        options.horizontal_edge = HorizontalEdge::Wrap;
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Front,
+            98,
+            0.0,
+            10,
             10,
         );
-        let (x, y) = new_drop.to_point();
-        assert_eq!(x, 10);
-        assert_eq!(y, 11);
+        let mut columns = std::collections::HashSet::new();
+        for _ in 0..100 {
+            new_drop.update(
+                (100, 200),
+                &options,
+                Duration::from_millis(50),
+                &mut rng,
+            );
+            assert!(new_drop.fx < 100);
+            columns.insert(new_drop.fx);
+        }
+        assert!(columns.iter().any(|x| *x <= 3));
+
+        options.horizontal_edge = HorizontalEdge::Clamp;
+        for _ in 0..100 {
+            new_drop.update(
+                (100, 200),
+                &options,
+                Duration::from_millis(50),
+                &mut rng,
+            );
+            assert!(new_drop.fx >= 93);
+        }
     }
 
     #[test]
-    fn to_point_vec() {
-        let new_drop = RainDrop::from_values(
+    fn tail_floor_jitter_varies_drops() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut options = get_sane_options();
+        options.tail_floor = 0.5;
+        options.tail_floor_jitter = 0.3;
+        let drops: Vec<RainDrop> = (1..=2)
+            .map(|index| {
+                let mut new_drop =
+                    RainDrop::new((100, 100), &options, index, &mut rng);
+                new_drop.body = vec!['a'; 10];
+                new_drop.fy = 50.0;
+                new_drop
+            })
+            .collect();
+        let floors: Vec<f32> =
+            drops.iter().map(|d| d.tail_floor(&options)).collect();
+        assert_ne!(floors[0], floors[1]);
+        for (new_drop, floor) in drops.iter().zip(floors) {
+            assert!((0.2..=0.8).contains(&floor));
+            let points = new_drop.to_points_styled(&options);
+            assert_eq!(points[0].intensity, 1.0);
+            assert!((points[9].intensity - floor).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn grow_with_fixed_chars() {
+        let mut rng = rand::rng();
+        let mut next = 'a';
+        let source = SharedCharSource::new(move || {
+            let c = next;
+            next = char::from_u32(next as u32 + 1).unwrap();
+            c
+        });
+        let mut new_drop = RainDrop::from_values(
             1,
-            vec!['a', 'b', 'c'],
-            RainDropStyle::Fading,
+            vec!['x'],
+            RainDropStyle::Front,
             10,
             10.0,
-            10,
-            8,
+            6,
+            20,
         );
-        let points = new_drop.to_points_vec();
-        assert_eq!(points.len(), 3);
-        assert_eq!(points[0], (10, 10, 'a'));
+        let options = get_sane_options();
+        let dt = Duration::from_millis(200);
+        new_drop.update_with((100, 100), &options, dt, &mut rng, &source);
+        assert_eq!(new_drop.length(), 5);
+        assert_eq!(new_drop.body, vec!['d', 'c', 'b', 'a', 'x']);
+
+        // growth over max length cuts the tail
+        new_drop.update_with((100, 100), &options, dt, &mut rng, &source);
+        assert_eq!(new_drop.length(), 6);
+        assert_eq!(new_drop.body, vec!['h', 'g', 'f', 'e', 'd', 'c']);
+
+        // slow drops get one character per update
+        new_drop.speed = 5;
+        new_drop.max_length = 10;
+        new_drop.update_with((100, 100), &options, dt, &mut rng, &source);
+        assert_eq!(new_drop.body[..2], ['i', 'h']);
     }
 
     #[test]
     fn grow() {
         let mut rng = rand::rng();
+        let mut unlimited = usize::MAX;
         let mut new_drop = RainDrop::from_values(
             1,
             vec!['a'],
@@ -329,8 +2681,8 @@ mod tests {
             20,
             10,
         );
-        new_drop.grow(10, &mut rng);
-        assert_eq!(new_drop.body.len(), 1);
+        new_drop.grow(10, &get_sane_options(), &mut unlimited, None, &mut rng);
+        assert_eq!(new_drop.length(), 1);
         assert_eq!(new_drop.body.first(), Some(&'a'));
 
         let mut new_drop = RainDrop::from_values(
@@ -342,11 +2694,11 @@ mod tests {
             20,
             4,
         );
-        new_drop.grow(12, &mut rng);
-        assert_eq!(new_drop.body.len(), 2);
+        new_drop.grow(12, &get_sane_options(), &mut unlimited, None, &mut rng);
+        assert_eq!(new_drop.length(), 2);
         assert_eq!(new_drop.body.get(1), Some(&'b'));
-        new_drop.grow(11, &mut rng);
-        assert_eq!(new_drop.body.len(), 2);
+        new_drop.grow(11, &get_sane_options(), &mut unlimited, None, &mut rng);
+        assert_eq!(new_drop.length(), 2);
 
         let mut new_drop = RainDrop::from_values(
             1,
@@ -358,9 +2710,48 @@ mod tests {
             4,
         );
         for _ in 1..10 {
-            new_drop.grow(12, &mut rng);
+            new_drop.grow(12, &get_sane_options(), &mut unlimited, None, &mut rng);
         }
-        assert_eq!(new_drop.body.len(), 3);
+        assert_eq!(new_drop.length(), 3);
+    }
+
+    #[test]
+    fn wiggle_scaled_by_aspect_ratio() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.wiggle = Some(WiggleOptions {
+            amplitude: 2.0,
+            frequency: 0.5,
+        });
+        let widest = |options: &DigitalRainOptions,
+                      rng: &mut rand::rngs::ThreadRng| {
+            let mut new_drop = RainDrop::from_values(
+                1,
+                vec!['a'],
+                RainDropStyle::Front,
+                50,
+                0.0,
+                10,
+                10,
+            );
+            (0..100)
+                .map(|_| {
+                    new_drop.update(
+                        (100, 200),
+                        options,
+                        Duration::from_millis(50),
+                        rng,
+                    );
+                    new_drop.fx.abs_diff(50)
+                })
+                .max()
+                .unwrap()
+        };
+
+        options.aspect_ratio = 1.0;
+        assert_eq!(widest(&options, &mut rng), 2);
+        options.aspect_ratio = 2.0;
+        assert_eq!(widest(&options, &mut rng), 4);
     }
 
     #[test]
@@ -463,6 +2854,56 @@ mod tests {
         assert_eq!(new_drop.fy, 33.0); // should be reseted there
     }
 
+    #[test]
+    fn update_clamps_long_stall() {
+        let mut rng = rand::rng();
+        let mut options = get_sane_options();
+        options.max_step = Duration::from_millis(200);
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b'],
+            RainDropStyle::Front,
+            10,
+            10.0,
+            50,
+            10,
+        );
+        new_drop.update((100, 100), &options, Duration::from_secs(60), &mut rng);
+        assert_eq!(new_drop.fy, 12.0);
+        assert_eq!(new_drop.body.len(), 4);
+    }
+
+    #[test]
+    fn exiting_drop_trims_offscreen_cells() {
+        let mut rng = rand::rng();
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c', 'd', 'e'],
+            RainDropStyle::Front,
+            10,
+            30.0,
+            10,
+            10,
+        );
+        new_drop.update(
+            (100, 30),
+            &get_sane_options(),
+            Duration::from_millis(200),
+            &mut rng,
+        );
+        assert_eq!(new_drop.body, vec!['d', 'e']);
+        assert_eq!(new_drop.to_points_vec(), vec![(10, 29, 'd'), (10, 28, 'e')]);
+
+        new_drop.update(
+            (100, 30),
+            &get_sane_options(),
+            Duration::from_millis(100),
+            &mut rng,
+        );
+        assert_eq!(new_drop.body, vec!['e']);
+        assert_eq!(new_drop.to_points_vec(), vec![(10, 29, 'e')]);
+    }
+
     #[test]
     fn out_of_bounds() {
         let mut rng = rand::rng();
@@ -479,7 +2920,7 @@ mod tests {
                     &get_sane_options(),
                     Duration::from_millis(100),
                     &mut rng,
-                )
+                );
             }
         }
     }
@@ -0,0 +1,41 @@
+//! Rasterization of rendered frames into RGBA pixels for recordings
+//! and thumbnails. Glyphs are approximated with solid blocks of their color.
+use super::draw::to_rgb;
+use crate::buffer::{Buffer, Cell};
+
+/// Pixels of cells without glyph
+const BACKGROUND: [u8; 4] = [0, 0, 0, 255];
+
+/// Rasterize buffer into RGBA pixels row by row, every cell becomes
+/// `cell_px` wide and tall block filled with its color
+pub fn rasterize(buffer: &Buffer, cell_px: (u32, u32)) -> Vec<u8> {
+    let (cell_width, cell_height) = (cell_px.0 as usize, cell_px.1 as usize);
+    let row_px = buffer.width * cell_width;
+    let mut pixels = BACKGROUND.repeat(row_px * buffer.height * cell_height);
+    for (index, cell) in buffer.iter().enumerate() {
+        if *cell == Cell::default() {
+            continue;
+        }
+        let (x, y) = buffer.pos_of(index);
+        let (r, g, b) = to_rgb(cell.color);
+        for py in y * cell_height..(y + 1) * cell_height {
+            for px in x * cell_width..(x + 1) * cell_width {
+                let offset = (py * row_px + px) * 4;
+                pixels[offset..offset + 4].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_buffer_is_background() {
+        let pixels = rasterize(&Buffer::new(3, 2), (4, 8));
+        assert_eq!(pixels.len(), 3 * 4 * 2 * 8 * 4);
+        assert!(pixels.chunks(4).all(|pixel| pixel == BACKGROUND));
+    }
+}
@@ -0,0 +1,202 @@
+//! Pure rain drop math without `std`, timing is passed in microseconds
+//! so it can drive displays which have no system clock. Random rolls take
+//! any `Rng`, so boards can plug in their own generator
+//!
+//! Only this module builds without `std`. `RainDrop` and `DigitalRain`
+//! take their movement, growth, phase and spawn math from here, but they
+//! stay behind the `std` feature along with options, colors and rendering.
+//! Embedders keep drop state on their side and call these functions
+
+use rand::Rng;
+
+/// Step of low discrepancy sequence used by `stagger_row`
+const GOLDEN_RATIO_CONJUGATE: f64 = 0.618_033_988_749_895;
+
+/// Drops faster than this grow by every row passed, slower ones grow
+/// by one cell whenever they move
+pub const CONTINUOUS_GROWTH_SPEED: u16 = 8;
+
+/// Position of a rain drop relative to the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPhase {
+    /// Tail not yet fully came out from the top
+    Entering,
+    /// Drop somewhere in the middle of the screen
+    Falling,
+    /// Head is below the bottom, tail is still visible
+    Exiting,
+    /// Drop is out of the screen and should be reset
+    Finished,
+}
+
+/// Rows passed with `speed` cells per second during `dt_micros`
+#[inline]
+pub fn advance_rows(speed: u16, dt_micros: u64) -> f32 {
+    (speed as u64 * dt_micros) as f32 / 1_000_000.0
+}
+
+/// Classify drop by rounded head and tail rows, tail row is negative
+/// while the tail is above the screen and it is compared as signed value
+#[inline]
+pub fn drop_phase(head_y: u16, tail_y: i16, height: u16) -> DropPhase {
+    if tail_y <= 0 {
+        DropPhase::Entering
+    } else if head_y <= height {
+        DropPhase::Falling
+    } else if (tail_y as i32) < height as i32 {
+        DropPhase::Exiting
+    } else {
+        DropPhase::Finished
+    }
+}
+
+/// Whether drop falling with `speed` grows by every row it passes
+#[inline]
+pub fn grows_continuously(speed: u16) -> bool {
+    speed > CONTINUOUS_GROWTH_SPEED
+}
+
+/// Cells to grow after the head passed `delta` rows and growth deferred
+/// to later updates. At most `room` cells fit into the body and at most
+/// `budget` are grown now, the rest is deferred
+pub fn growth(
+    speed: u16,
+    pending: usize,
+    delta: usize,
+    room: usize,
+    budget: usize,
+) -> (usize, usize) {
+    if !grows_continuously(speed) {
+        return (usize::from(delta > 0 && budget > 0), pending);
+    }
+    let wanted = (pending + delta).min(room);
+    let inserts = wanted.min(budget);
+    (inserts, wanted - inserts)
+}
+
+/// Number of not yet trimmed body cells below the bottom row
+#[inline]
+pub fn offscreen_cells(head_y: i32, height: u16, trimmed: usize) -> usize {
+    let offscreen = (head_y - height as i32 + 1).max(0) as usize;
+    offscreen.saturating_sub(trimmed)
+}
+
+/// Head row of drop `index` on golden ratio sequence, consecutive drops
+/// are spread evenly over `height` rows instead of moving in waves
+pub fn stagger_row(index: usize, height: u16) -> u16 {
+    let position = index as f64 * GOLDEN_RATIO_CONJUGATE;
+    let fract = position - position as u64 as f64;
+    (fract * height as f64) as u16
+}
+
+/// Number of rows near the top where heads of drops created on start are
+pub fn start_band(height: u16) -> u16 {
+    height / 4
+}
+
+/// Random head row near the top for a drop created on start
+pub fn start_row<R: Rng + ?Sized>(height: u16, rng: &mut R) -> u16 {
+    rng.random_range(0..start_band(height).max(1))
+}
+
+/// Random max length of a drop created on start, drops on short screens
+/// are still a few cells long
+pub fn spawn_length<R: Rng + ?Sized>(height: u16, rng: &mut R) -> usize {
+    rng.random_range(4..=(2 * height / 3).max(4)) as usize
+}
+
+/// Random max length of a drop starting over from the top
+pub fn reset_length<R: Rng + ?Sized>(height: u16, rng: &mut R) -> usize {
+    let min = height / 4 + 1;
+    rng.random_range(min..=(height / 2).max(min)) as usize
+}
+
+/// Random max length from `range`, drops are at least one cell long
+pub fn pick_length<R: Rng + ?Sized>(range: (u16, u16), rng: &mut R) -> usize {
+    let (min, max) = (range.0.min(range.1).max(1), range.0.max(range.1).max(1));
+    rng.random_range(min..=max) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::SmallRng};
+
+    #[test]
+    fn advance() {
+        assert_eq!(advance_rows(10, 1_000_000), 10.0);
+        assert_eq!(advance_rows(10, 50_000), 0.5);
+        assert_eq!(advance_rows(0, 50_000), 0.0);
+    }
+
+    #[test]
+    fn phases() {
+        assert_eq!(drop_phase(3, -2, 30), DropPhase::Entering);
+        assert_eq!(drop_phase(40, 0, 30), DropPhase::Entering);
+        assert_eq!(drop_phase(20, 10, 30), DropPhase::Falling);
+        assert_eq!(drop_phase(30, 25, 30), DropPhase::Falling);
+        assert_eq!(drop_phase(35, 25, 30), DropPhase::Exiting);
+        assert_eq!(drop_phase(40, 30, 30), DropPhase::Finished);
+    }
+
+    #[test]
+    fn tail_above_screen_with_head_below() {
+        // long drop spans the whole screen
+        assert_eq!(drop_phase(35, -1, 30), DropPhase::Entering);
+        assert_eq!(drop_phase(35, 1, 30), DropPhase::Exiting);
+        // height which does not fit into i16
+        assert_eq!(drop_phase(40_000, 100, 40_000), DropPhase::Falling);
+        assert_eq!(drop_phase(40_001, 100, 40_000), DropPhase::Exiting);
+    }
+
+    #[test]
+    fn growth_within_room_and_budget() {
+        assert_eq!(growth(10, 0, 3, 10, usize::MAX), (3, 0));
+        assert_eq!(growth(10, 2, 3, 10, 4), (4, 1));
+        assert_eq!(growth(10, 2, 3, 4, usize::MAX), (4, 0));
+        // slow drops grow by one cell per move and keep pending growth
+        assert_eq!(growth(5, 2, 3, 10, usize::MAX), (1, 2));
+        assert_eq!(growth(5, 0, 0, 10, usize::MAX), (0, 0));
+        assert_eq!(growth(5, 0, 3, 10, 0), (0, 0));
+    }
+
+    #[test]
+    fn offscreen() {
+        assert_eq!(offscreen_cells(20, 30, 0), 0);
+        assert_eq!(offscreen_cells(32, 30, 0), 3);
+        assert_eq!(offscreen_cells(32, 30, 2), 1);
+        assert_eq!(offscreen_cells(32, 30, 5), 0);
+    }
+
+    #[test]
+    fn stagger_rows_spread() {
+        let rows: [u16; 8] = core::array::from_fn(|index| stagger_row(index, 40));
+        assert_eq!(rows[0], 0);
+        assert!(rows.iter().all(|row| *row < 40));
+        // every quarter of the screen gets a head
+        for quarter in 0..4 {
+            assert!(rows.iter().any(|row| row / 10 == quarter));
+        }
+    }
+
+    #[test]
+    fn rolls_within_ranges() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        for _ in 0..100 {
+            assert!(start_row(40, &mut rng) < 10);
+            assert!((4..=26).contains(&spawn_length(40, &mut rng)));
+            assert!((11..=20).contains(&reset_length(40, &mut rng)));
+            assert!((1..=3).contains(&pick_length((3, 0), &mut rng)));
+        }
+    }
+
+    #[test]
+    fn rolls_on_short_screens() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        for height in 0..8 {
+            assert!(start_row(height, &mut rng) <= start_band(height));
+            assert!(spawn_length(height, &mut rng) >= 4);
+            assert!(reset_length(height, &mut rng) > start_band(height).into());
+        }
+    }
+}